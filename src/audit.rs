@@ -0,0 +1,46 @@
+use crate::id::RunId;
+use crate::runner::JobHandle;
+use chrono::{DateTime, Utc};
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize), serde(rename_all = "snake_case"))]
+pub enum AuditAction {
+    Triggered,
+    Deduplicated,
+    SampledOut,
+    GatedOff,
+    /// The job is disabled; see [`JobRunner::disable`](crate::JobRunner::disable).
+    Disabled,
+    /// The run would have started outside the job's
+    /// [`occurrence_window`](crate::JobBuilder::occurrence_window).
+    Missed,
+    /// The named heartbeat source had not checked in recently enough.
+    StaleHeartbeat(String),
+    /// Another instance claimed the run of a singleton job.
+    ClaimedElsewhere,
+    /// A manual trigger waiting for its exclusive group or the concurrency limit.
+    Queued,
+}
+
+/// What a manual trigger does to the job's regular schedule.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TriggerMode {
+    /// Scheduled runs keep firing at their usual times.
+    #[default]
+    KeepCadence,
+    /// The next scheduled run is computed from the trigger, as if the job had
+    /// just run on schedule.
+    ResetCadence,
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct AuditEntry {
+    pub at: DateTime<Utc>,
+    pub job: JobHandle,
+    pub action: AuditAction,
+    /// The run started by this action, if any.
+    pub run_id: Option<RunId>,
+}
@@ -0,0 +1,748 @@
+//! `jobschedd`: runs the shell commands listed in a TOML file on their
+//! schedules. It is the reference integration of the library: configuration
+//! through [`job_scheduler::config`], job state kept in a file across
+//! restarts, a small HTTP admin API with Prometheus metrics, graceful
+//! shutdown on SIGINT and SIGTERM and a config reload on SIGHUP.
+//!
+//! ```toml
+//! [daemon]
+//! state_file = "/var/lib/jobschedd/state"
+//! admin_addr = "127.0.0.1:9180"
+//! shutdown_timeout = "30 seconds"
+//! # Only used with --daemon.
+//! pidfile = "/run/jobschedd.pid"
+//! log_file = "/var/log/jobschedd.log"
+//!
+//! [[job]]
+//! name = "backup"
+//! every = "1 day"
+//! at = "02:00"
+//! command = "/usr/local/bin/backup"
+//! ```
+//!
+//! `jobschedd [--daemon] <config.toml>` runs in the foreground, or detached
+//! with a pidfile on unix. On Windows, `jobschedd --service <config.toml>` is
+//! the command line to register with the service manager.
+//! `jobschedd --schema` prints the JSON Schema of the config file.
+//!
+//! A reload re-reads the jobs and `[runner]` settings; jobs keep their next
+//! run across it if their name is unchanged. `[daemon]` settings need a
+//! restart.
+//!
+//! Admin API: `GET /jobs`, `GET /metrics`, `POST /jobs/<name>/trigger`,
+//! `POST /heartbeats/<source>`, `POST /pause`, `POST /resume`, `POST /reload`
+//! and `POST /shutdown`.
+
+use chrono::{DateTime, Utc};
+use job_scheduler::config::{parse_duration, Config, JobConfig};
+use job_scheduler::{
+    Event, IdGenerator, JobHandle, JobId, JobOutcome, JobRunner, JobState, JobStore,
+    OverflowPolicy, StoreError, StoreUpdate, UuidGenerator,
+};
+use serde::Deserialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::error::Error;
+use std::fmt::Write as _;
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::process::{Command, ExitCode};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// Set by the signal handlers and `POST /shutdown`.
+static STOP: AtomicBool = AtomicBool::new(false);
+/// Set by SIGHUP and `POST /reload`.
+static RELOAD: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Default, Deserialize)]
+struct DaemonFile {
+    #[serde(default)]
+    daemon: DaemonConfig,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DaemonConfig {
+    state_file: Option<PathBuf>,
+    admin_addr: Option<String>,
+    /// How long to wait for running commands on shutdown, e.g. `"30 seconds"`.
+    shutdown_timeout: Option<String>,
+    /// Used by `--daemon`, which only exists on unix.
+    #[cfg_attr(not(unix), allow(dead_code))]
+    pidfile: Option<PathBuf>,
+    #[cfg_attr(not(unix), allow(dead_code))]
+    log_file: Option<PathBuf>,
+}
+
+enum Mode {
+    Foreground,
+    Daemon,
+    Service,
+}
+
+fn main() -> ExitCode {
+    let arguments: Vec<String> = std::env::args().skip(1).collect();
+    let (mode, path) = match arguments.as_slice() {
+        [flag] if flag == "--schema" => {
+            println!("{:#}", Config::json_schema().as_value());
+            return ExitCode::SUCCESS;
+        }
+        [path] => (Mode::Foreground, path),
+        [flag, path] if flag == "--daemon" => (Mode::Daemon, path),
+        [flag, path] if flag == "--service" => (Mode::Service, path),
+        _ => {
+            eprintln!("usage: jobschedd [--daemon | --service] <config.toml> | --schema");
+            return ExitCode::from(2);
+        }
+    };
+    let result = match mode {
+        Mode::Foreground => run(path),
+        Mode::Daemon => run_detached(path),
+        Mode::Service => service::run(path.clone()),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("jobschedd: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(path: &str) -> Result<(), Box<dyn Error>> {
+    let daemon = Daemon::start(path)?;
+    daemon.runner.run_forever();
+    daemon.join();
+    Ok(())
+}
+
+#[cfg(unix)]
+fn run_detached(path: &str) -> Result<(), Box<dyn Error>> {
+    let (_, settings) = read_config(path)?;
+    let (Some(pidfile), Some(log_file)) = (settings.pidfile, settings.log_file) else {
+        return Err("--daemon needs `pidfile` and `log_file` in [daemon]".into());
+    };
+    // Threads do not survive the fork, so detach before starting any.
+    let pidfile = job_scheduler::daemon::detach(pidfile, log_file)?;
+    run(path)?;
+    Ok(pidfile.remove()?)
+}
+
+#[cfg(not(unix))]
+fn run_detached(_path: &str) -> Result<(), Box<dyn Error>> {
+    Err("--daemon is only supported on unix; use --service on Windows".into())
+}
+
+/// A running runner plus the thread that shuts it down or reloads it.
+struct Daemon {
+    runner: Arc<JobRunner>,
+    /// The service manager drives the shutdown itself.
+    #[cfg(windows)]
+    shutdown_timeout: Duration,
+    control: JoinHandle<()>,
+}
+
+impl Daemon {
+    fn start(path: &str) -> Result<Daemon, Box<dyn Error>> {
+        let (config, settings) = read_config(path)?;
+        let shutdown_timeout = match &settings.shutdown_timeout {
+            Some(text) => parse_duration(text)?,
+            None => Duration::from_secs(30),
+        };
+
+        let runner = Arc::new(JobRunner::new());
+        let ids = NamedIds::default();
+        runner.set_id_generator(ids.clone());
+        // Even without a state file, the store carries next runs across reloads.
+        runner.set_store(match settings.state_file {
+            Some(path) => FileStore::open(path)?,
+            None => FileStore::in_memory(),
+        });
+        // Logging to a slow terminal or file must not delay the jobs.
+        runner.set_event_queue(1024, OverflowPolicy::DropOldest);
+        runner.add_listener(log_event);
+        quiet_task_panics();
+        let mut jobs = Jobs {
+            path: path.to_string(),
+            ids,
+            handles: vec![],
+        };
+        jobs.apply(&runner, &config)?;
+
+        if let Some(addr) = &settings.admin_addr {
+            let listener = TcpListener::bind(addr).map_err(|error| format!("{addr}: {error}"))?;
+            let runner = Arc::clone(&runner);
+            std::thread::Builder::new()
+                .name("admin".to_string())
+                .spawn(move || serve(listener, &runner))?;
+        }
+        install_signal_handlers();
+
+        let control = {
+            let runner = Arc::clone(&runner);
+            std::thread::Builder::new()
+                .name("control".to_string())
+                .spawn(move || {
+                    while !STOP.load(Ordering::SeqCst) {
+                        if RELOAD.swap(false, Ordering::SeqCst) {
+                            if let Err(error) = jobs.reload(&runner) {
+                                eprintln!(
+                                    "jobschedd: reload failed, keeping the old jobs: {error}"
+                                );
+                            }
+                        }
+                        std::thread::sleep(Duration::from_millis(100));
+                    }
+                    let report = runner.shutdown(shutdown_timeout);
+                    for (_, run_id) in report.still_running {
+                        eprintln!("jobschedd: {run_id} still running at exit");
+                    }
+                })?
+        };
+        Ok(Daemon {
+            runner,
+            #[cfg(windows)]
+            shutdown_timeout,
+            control,
+        })
+    }
+
+    /// Waits for the control thread, telling it to stop if the runner was
+    /// stopped some other way.
+    fn join(self) {
+        STOP.store(true, Ordering::SeqCst);
+        self.control.join().expect("control thread panicked");
+    }
+}
+
+fn read_config(path: &str) -> Result<(Config, DaemonConfig), Box<dyn Error>> {
+    let text = std::fs::read_to_string(path).map_err(|error| format!("{path}: {error}"))?;
+    let config = Config::parse_with_extensions(&text, &["daemon"], &["command"])?;
+    let settings = toml::from_str::<DaemonFile>(&text)?.daemon;
+    Ok((config, settings))
+}
+
+/// The jobs registered from the config file, replaced on reload.
+struct Jobs {
+    path: String,
+    ids: NamedIds,
+    handles: Vec<JobHandle>,
+}
+
+impl Jobs {
+    fn reload(&mut self, runner: &JobRunner) -> Result<(), Box<dyn Error>> {
+        let (config, _) = read_config(&self.path)?;
+        self.apply(runner, &config)
+    }
+
+    /// Replaces the registered jobs with those of `config`, leaving them
+    /// untouched if a command is missing.
+    fn apply(&mut self, runner: &JobRunner, config: &Config) -> Result<(), Box<dyn Error>> {
+        let mut commands = vec![];
+        for job in &config.jobs {
+            commands.push(command(job)?);
+        }
+        for handle in self.handles.drain(..) {
+            runner.cancel(handle);
+        }
+        self.ids.expect(config);
+        let mut commands = commands.into_iter();
+        self.handles = config.apply(runner, |_| {
+            let command = commands.next().expect("one command per job");
+            move || run_command(&command)
+        })?;
+        for line in runner.describe_all().lines() {
+            eprintln!("jobschedd: {line}");
+        }
+        Ok(())
+    }
+}
+
+fn command(job: &JobConfig) -> Result<String, String> {
+    match job.extra.get("command") {
+        Some(toml::Value::String(command)) => Ok(command.clone()),
+        Some(_) => Err(format!("job `{}`: `command` must be a string", job.name)),
+        None => Err(format!("job `{}`: missing `command`", job.name)),
+    }
+}
+
+/// Runs `command` through the shell. A nonzero exit panics, so the run is
+/// recorded as failed.
+fn run_command(command: &str) {
+    let (shell, flag) = if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+    let status = Command::new(shell)
+        .arg(flag)
+        .arg(command)
+        .status()
+        .unwrap_or_else(|error| panic!("cannot start `{command}`: {error}"));
+    if !status.success() {
+        panic!("`{command}` failed: {status}");
+    }
+}
+
+/// Failed commands are already logged when their run finishes, so the
+/// default panic message is only printed for the daemon's own threads, which
+/// are all named. Task threads are not.
+fn quiet_task_panics() {
+    let default = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if std::thread::current().name().is_some() {
+            default(info);
+        }
+    }));
+}
+
+fn log_event(event: &Event) {
+    match event {
+        Event::RunStarted { run_id, .. } => eprintln!("jobschedd: {run_id} started"),
+        Event::RunFinished(record) => {
+            let outcome = match &record.outcome {
+                JobOutcome::Success => "succeeded".to_string(),
+                JobOutcome::Panicked(message) => format!("failed: {message}"),
+                JobOutcome::TimedOut => "timed out".to_string(),
+                JobOutcome::Skipped => "skipped".to_string(),
+            };
+            eprintln!("jobschedd: {} ({}) {outcome}", record.run_id, record.job_id);
+        }
+        Event::StoreDegraded { error } => eprintln!("jobschedd: state file unavailable: {error}"),
+        Event::StoreRecovered { flushed, dropped } => eprintln!(
+            "jobschedd: state file recovered, {flushed} updates written, {dropped} dropped"
+        ),
+        Event::CompletionOverdue { job, last_success } => match last_success {
+            Some(at) => eprintln!("jobschedd: {job} has not succeeded since {at}"),
+            None => eprintln!("jobschedd: {job} has not succeeded yet"),
+        },
+        Event::JobDisabled {
+            job,
+            consecutive_panics,
+        } => eprintln!("jobschedd: {job} disabled after {consecutive_panics} panics in a row"),
+        Event::SloBreached {
+            job,
+            success_rate,
+            target,
+        } => eprintln!(
+            "jobschedd: {job} success rate {:.2}% is below its {:.2}% target",
+            success_rate * 100.0,
+            target * 100.0
+        ),
+        Event::CallbackPanicked {
+            job,
+            callback,
+            message,
+        } => eprintln!("jobschedd: {callback} callback of {job} panicked: {message}"),
+    }
+}
+
+/// Uses each job's configured name as its id, so the state file survives
+/// jobs being reordered, added or reloaded. Anything else the runner
+/// registers, such as work given to `submit_now`, gets a random id.
+#[derive(Clone, Default)]
+struct NamedIds {
+    names: Arc<Mutex<VecDeque<String>>>,
+    runs: Arc<AtomicU64>,
+}
+
+impl NamedIds {
+    /// Queues the names of the jobs `config` is about to register, in order.
+    fn expect(&self, config: &Config) {
+        *self.names.lock().unwrap() = config.jobs.iter().map(|job| job.name.clone()).collect();
+    }
+}
+
+impl IdGenerator for NamedIds {
+    fn job_id(&self) -> String {
+        self.names
+            .lock()
+            .unwrap()
+            .pop_front()
+            .unwrap_or_else(|| UuidGenerator.job_id())
+    }
+
+    fn run_id(&self) -> String {
+        format!("run-{}", self.runs.fetch_add(1, Ordering::Relaxed) + 1)
+    }
+}
+
+/// Each job's next run and remaining count, one tab-separated line per job.
+/// Run records are not kept; the history is in memory only.
+struct FileStore {
+    /// `None` keeps the state in memory only.
+    path: Option<PathBuf>,
+    jobs: BTreeMap<String, (Option<DateTime<Utc>>, Option<i32>)>,
+}
+
+impl FileStore {
+    fn open(path: PathBuf) -> Result<Self, String> {
+        let invalid = |line: &str| format!("{}: invalid line `{line}`", path.display());
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(error) => return Err(format!("{}: {error}", path.display())),
+        };
+        let mut jobs = BTreeMap::new();
+        for line in text.lines() {
+            let mut fields = line.split('\t');
+            let (Some(id), Some(next_run), Some(remaining), None) =
+                (fields.next(), fields.next(), fields.next(), fields.next())
+            else {
+                return Err(invalid(line));
+            };
+            let next_run = match next_run {
+                "-" => None,
+                time => Some(
+                    DateTime::parse_from_rfc3339(time)
+                        .map_err(|_| invalid(line))?
+                        .with_timezone(&Utc),
+                ),
+            };
+            let remaining = match remaining {
+                "-" => None,
+                count => Some(count.parse().map_err(|_| invalid(line))?),
+            };
+            jobs.insert(id.to_string(), (next_run, remaining));
+        }
+        Ok(FileStore {
+            path: Some(path),
+            jobs,
+        })
+    }
+
+    fn in_memory() -> Self {
+        FileStore {
+            path: None,
+            jobs: BTreeMap::new(),
+        }
+    }
+
+    /// Rewrites the whole file through a temporary one, so a crash never
+    /// leaves it half written.
+    fn write(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let mut text = String::new();
+        for (id, (next_run, remaining)) in &self.jobs {
+            let next_run = next_run.map_or("-".to_string(), |time| time.to_rfc3339());
+            let remaining = remaining.map_or("-".to_string(), |count| count.to_string());
+            writeln!(text, "{id}\t{next_run}\t{remaining}").expect("writing to a String");
+        }
+        let temporary = path.with_extension("tmp");
+        std::fs::write(&temporary, text)?;
+        std::fs::rename(&temporary, path)
+    }
+}
+
+impl JobStore for FileStore {
+    fn save(&mut self, update: &StoreUpdate) -> Result<(), StoreError> {
+        let StoreUpdate::Job(state) = update else {
+            return Ok(());
+        };
+        let entry = (state.next_run, state.remaining_runs);
+        if self.jobs.get(state.job_id.as_str()) == Some(&entry) {
+            return Ok(());
+        }
+        self.jobs.insert(state.job_id.to_string(), entry);
+        self.write()
+            .map_err(|error| StoreError::new(error.to_string()))
+    }
+
+    fn load(&mut self, job_id: &JobId) -> Result<Option<JobState>, StoreError> {
+        Ok(self
+            .jobs
+            .get(job_id.as_str())
+            .map(|&(next_run, remaining_runs)| JobState {
+                job_id: job_id.clone(),
+                next_run,
+                remaining_runs,
+            }))
+    }
+}
+
+/// Answers admin requests one connection at a time.
+fn serve(listener: TcpListener, runner: &JobRunner) {
+    for stream in listener.incoming() {
+        let Ok(mut stream) = stream else {
+            continue;
+        };
+        let _ = stream.set_read_timeout(Some(Duration::from_secs(5)));
+        let (status, body) = match read_request_line(&mut stream) {
+            Some((method, path)) => handle(runner, &method, &path),
+            None => ("400 Bad Request", String::new()),
+        };
+        let _ = write!(
+            stream,
+            "HTTP/1.1 {status}\r\nContent-Type: text/plain; charset=utf-8\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        );
+    }
+}
+
+fn read_request_line(stream: &mut TcpStream) -> Option<(String, String)> {
+    let mut buffer = vec![0; 8192];
+    let mut len = 0;
+    while !buffer[..len].windows(4).any(|window| window == b"\r\n\r\n") {
+        if len == buffer.len() {
+            return None;
+        }
+        match stream.read(&mut buffer[len..]) {
+            Ok(0) | Err(_) => return None,
+            Ok(read) => len += read,
+        }
+    }
+    let request = std::str::from_utf8(&buffer[..len]).ok()?;
+    let mut parts = request.lines().next()?.split(' ');
+    Some((parts.next()?.to_string(), parts.next()?.to_string()))
+}
+
+fn handle(runner: &JobRunner, method: &str, path: &str) -> (&'static str, String) {
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    match (method, segments.as_slice()) {
+        ("GET", ["jobs"]) => ("200 OK", list_jobs(runner)),
+        ("GET", ["metrics"]) => ("200 OK", prometheus(runner)),
+        ("POST", ["jobs", name, "trigger"]) => {
+            let Some(name) = percent_decode(name) else {
+                return ("400 Bad Request", "invalid job name\n".to_string());
+            };
+            match runner.trigger_by_name(&name) {
+                Some(action) => ("200 OK", format!("{action:?}\n")),
+                None => ("404 Not Found", format!("no job named `{name}`\n")),
+            }
+        }
+        ("POST", ["heartbeats", source]) => {
+            let Some(source) = percent_decode(source) else {
+                return ("400 Bad Request", "invalid source\n".to_string());
+            };
+            runner.heartbeat(&source);
+            ("200 OK", "ok\n".to_string())
+        }
+        ("POST", ["pause"]) => {
+            runner.pause_all();
+            ("200 OK", "paused\n".to_string())
+        }
+        ("POST", ["resume"]) => {
+            runner.resume_all();
+            ("200 OK", "resumed\n".to_string())
+        }
+        ("POST", ["reload"]) => {
+            RELOAD.store(true, Ordering::SeqCst);
+            ("200 OK", "reloading\n".to_string())
+        }
+        ("POST", ["shutdown"]) => {
+            STOP.store(true, Ordering::SeqCst);
+            ("200 OK", "shutting down\n".to_string())
+        }
+        (
+            _,
+            ["jobs" | "metrics" | "pause" | "resume" | "reload" | "shutdown"]
+            | ["jobs", _, "trigger"]
+            | ["heartbeats", _],
+        ) => ("405 Method Not Allowed", String::new()),
+        _ => ("404 Not Found", String::new()),
+    }
+}
+
+/// Decodes `%20`-style escapes in a path segment, or `None` if they are
+/// malformed or not UTF-8.
+fn percent_decode(segment: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(segment.len());
+    let mut rest = segment.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = tail
+                .get(..2)
+                .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))?;
+            bytes.push(u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// One line per job: name, state, next run and schedule.
+fn list_jobs(runner: &JobRunner) -> String {
+    let mut text = String::new();
+    for job in runner.jobs() {
+        let name = job.name.unwrap_or_default();
+        let next_run = job
+            .next_run
+            .map_or("-".to_string(), |time| time.to_rfc3339());
+        let state = if job.running { "running" } else { "idle" };
+        let _ = writeln!(text, "{name}\t{state}\t{next_run}\t{}", job.schedule);
+    }
+    text
+}
+
+fn prometheus(runner: &JobRunner) -> String {
+    let snapshot = runner.metrics_snapshot();
+    let mut text = String::new();
+    let _ = writeln!(text, "# TYPE jobschedd_runs_total counter");
+    for job in &snapshot.jobs {
+        let _ = writeln!(
+            text,
+            "jobschedd_runs_total{{job=\"{}\"}} {}",
+            label_value(job.job_id.as_str()),
+            job.runs_total
+        );
+    }
+    let _ = writeln!(text, "# TYPE jobschedd_failures_total counter");
+    for job in &snapshot.jobs {
+        let _ = writeln!(
+            text,
+            "jobschedd_failures_total{{job=\"{}\"}} {}",
+            label_value(job.job_id.as_str()),
+            job.failures_total
+        );
+    }
+    let _ = writeln!(text, "# TYPE jobschedd_run_duration_seconds histogram");
+    for job in &snapshot.jobs {
+        let labels = format!("job=\"{}\"", label_value(job.job_id.as_str()));
+        histogram(
+            &mut text,
+            "jobschedd_run_duration_seconds",
+            &labels,
+            &job.run_duration,
+        );
+    }
+    let _ = writeln!(text, "# TYPE jobschedd_schedule_lag_seconds histogram");
+    for job in &snapshot.jobs {
+        let labels = format!("job=\"{}\"", label_value(job.job_id.as_str()));
+        histogram(
+            &mut text,
+            "jobschedd_schedule_lag_seconds",
+            &labels,
+            &job.schedule_lag,
+        );
+    }
+    let runtime = &snapshot.runtime;
+    let _ = writeln!(text, "# TYPE jobschedd_tick_duration_seconds histogram");
+    histogram(
+        &mut text,
+        "jobschedd_tick_duration_seconds",
+        "",
+        &runtime.tick_duration,
+    );
+    for (name, value) in [
+        ("registered_jobs", runtime.registered_jobs),
+        ("running_tasks", runtime.running_tasks),
+        ("pending_runs", runtime.pending_runs),
+        ("store_buffer_len", runtime.store_buffer_len),
+        ("queued_events", runtime.queued_events),
+    ] {
+        let _ = writeln!(
+            text,
+            "# TYPE jobschedd_{name} gauge\njobschedd_{name} {value}"
+        );
+    }
+    let _ = writeln!(
+        text,
+        "# TYPE jobschedd_dropped_events_total counter\njobschedd_dropped_events_total {}",
+        runtime.dropped_events
+    );
+    let _ = writeln!(
+        text,
+        "# TYPE jobschedd_paused gauge\njobschedd_paused {}",
+        u8::from(runner.is_paused())
+    );
+    text
+}
+
+/// Escapes a label value as the Prometheus text format requires.
+fn label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn histogram(text: &mut String, name: &str, labels: &str, histogram: &job_scheduler::Histogram) {
+    let separator = if labels.is_empty() { "" } else { "," };
+    let bounds = histogram.bounds.iter().map(f64::to_string);
+    for (bound, count) in bounds.chain(["+Inf".to_string()]).zip(&histogram.counts) {
+        let _ = writeln!(
+            text,
+            "{name}_bucket{{{labels}{separator}le=\"{bound}\"}} {count}"
+        );
+    }
+    let labels = if labels.is_empty() {
+        String::new()
+    } else {
+        format!("{{{labels}}}")
+    };
+    let _ = writeln!(text, "{name}_sum{labels} {}", histogram.sum);
+    let _ = writeln!(text, "{name}_count{labels} {}", histogram.count);
+}
+
+#[cfg(unix)]
+fn install_signal_handlers() {
+    extern "C" fn on_stop(_: libc::c_int) {
+        STOP.store(true, Ordering::SeqCst);
+    }
+    extern "C" fn on_reload(_: libc::c_int) {
+        RELOAD.store(true, Ordering::SeqCst);
+    }
+    let stop = on_stop as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    let reload = on_reload as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    unsafe {
+        libc::signal(libc::SIGINT, stop);
+        libc::signal(libc::SIGTERM, stop);
+        libc::signal(libc::SIGHUP, reload);
+    }
+}
+
+/// Without Unix signals, stop the daemon through `POST /shutdown`.
+#[cfg(not(unix))]
+fn install_signal_handlers() {}
+
+#[cfg(windows)]
+mod service {
+    use super::Daemon;
+    use std::error::Error;
+    use std::ffi::OsString;
+    use std::sync::OnceLock;
+
+    const NAME: &str = "jobschedd";
+
+    static CONFIG: OnceLock<String> = OnceLock::new();
+
+    windows_service::define_windows_service!(ffi_service_main, service_main);
+
+    /// Hands the process to the service manager, which calls `service_main`.
+    pub(super) fn run(path: String) -> Result<(), Box<dyn Error>> {
+        CONFIG.set(path).expect("run once");
+        windows_service::service_dispatcher::start(NAME, ffi_service_main)?;
+        Ok(())
+    }
+
+    fn service_main(_arguments: Vec<OsString>) {
+        let path = CONFIG.get().expect("set before dispatching");
+        let daemon = match Daemon::start(path) {
+            Ok(daemon) => daemon,
+            Err(error) => return eprintln!("jobschedd: {error}"),
+        };
+        let runner = daemon.runner.clone();
+        if let Err(error) = job_scheduler::service::run(NAME, runner, daemon.shutdown_timeout) {
+            eprintln!("jobschedd: {error}");
+        }
+        daemon.join();
+    }
+}
+
+#[cfg(not(windows))]
+mod service {
+    use std::error::Error;
+
+    pub(super) fn run(_path: String) -> Result<(), Box<dyn Error>> {
+        Err("--service is only supported on Windows".into())
+    }
+}
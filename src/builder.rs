@@ -0,0 +1,554 @@
+use crate::cache::{CacheRefresh, Shared};
+use crate::completion::{Completer, Completion};
+use crate::dependency::{AfterJobs, RunCondition};
+use crate::job::{Job, Scheduler};
+use crate::run::JobContext;
+use crate::runner::{
+    Experiment, JobHandle, JobOptions, OccurrenceWindow, SchedulerClient, Watchdog,
+};
+use crate::schedule::{largest_unit, MonthDay, Schedule, TimeUnit};
+use crate::slo::Slo;
+#[cfg(feature = "solar")]
+use crate::solar::Solar;
+use crate::source::{EventSource, OnEvent};
+use crate::store::Delivery;
+use chrono::{DateTime, Duration, NaiveTime, Utc, Weekday};
+use std::sync::Arc;
+
+pub struct JobBuilder<'a> {
+    interval: u64,
+    time_unit: Option<TimeUnit>,
+    at_time: Option<NaiveTime>,
+    client: &'a SchedulerClient,
+    weekday: Option<Weekday>,
+    repeat: Option<i32>,
+    phase: Option<Duration>,
+    month_day: Option<MonthDay>,
+    starts: Option<DateTime<Utc>>,
+    ends: Option<DateTime<Utc>>,
+    window: Option<(NaiveTime, NaiveTime)>,
+    except_weekends: bool,
+    custom: Option<Box<dyn Scheduler>>,
+    #[cfg(feature = "solar")]
+    solar: Option<Solar>,
+    first_run: Option<FirstRun>,
+    options: JobOptions,
+}
+
+#[derive(Clone, Copy)]
+enum FirstRun {
+    Immediately,
+    Deferred,
+}
+
+impl<'a> JobBuilder<'a> {
+    pub(crate) fn new(client: &'a SchedulerClient, interval: u64) -> Self {
+        JobBuilder {
+            interval,
+            client,
+            time_unit: None,
+            at_time: None,
+            weekday: None,
+            repeat: None,
+            phase: None,
+            month_day: None,
+            starts: None,
+            ends: None,
+            window: None,
+            except_weekends: false,
+            custom: None,
+            #[cfg(feature = "solar")]
+            solar: None,
+            first_run: None,
+            options: JobOptions::default(),
+        }
+    }
+
+    pub(crate) fn with_scheduler(
+        client: &'a SchedulerClient,
+        scheduler: Box<dyn Scheduler>,
+    ) -> Self {
+        JobBuilder {
+            custom: Some(scheduler),
+            ..JobBuilder::new(client, 0)
+        }
+    }
+
+    #[cfg(feature = "solar")]
+    pub(crate) fn with_solar(client: &'a SchedulerClient, solar: Solar) -> Self {
+        JobBuilder {
+            solar: Some(solar),
+            ..JobBuilder::new(client, 0)
+        }
+    }
+
+    pub(crate) fn with_source(client: &'a SchedulerClient, source: Box<dyn EventSource>) -> Self {
+        let mut builder = JobBuilder::with_scheduler(client, Box::new(OnEvent));
+        builder.options.source = Some(source);
+        builder
+    }
+
+    pub(crate) fn with_schedule(client: &'a SchedulerClient, schedule: Schedule) -> Self {
+        JobBuilder {
+            time_unit: Some(schedule.time_unit),
+            at_time: schedule.at_time,
+            weekday: schedule.weekday,
+            repeat: schedule.repeat,
+            phase: schedule.phase,
+            month_day: schedule.month_day,
+            starts: schedule.starts,
+            ends: schedule.ends,
+            window: schedule.window,
+            except_weekends: schedule.except_weekends,
+            ..JobBuilder::new(client, schedule.interval)
+        }
+    }
+
+    pub(crate) fn with_interval(
+        client: &'a SchedulerClient,
+        interval: std::time::Duration,
+    ) -> Self {
+        assert!(
+            interval >= std::time::Duration::from_millis(1),
+            "every_duration expects at least 1ms, got {interval:?}"
+        );
+        let millis = i64::try_from(interval.as_millis())
+            .ok()
+            .and_then(Duration::try_milliseconds)
+            .unwrap_or_else(|| panic!("every_duration interval {interval:?} is too large"));
+        let (count, unit) = largest_unit(millis);
+        JobBuilder {
+            time_unit: Some(unit),
+            ..JobBuilder::new(client, count as u64)
+        }
+    }
+
+    pub fn milliseconds(mut self) -> Self {
+        self.time_unit = Some(TimeUnit::Milliseconds);
+        self
+    }
+    pub fn seconds(mut self) -> Self {
+        self.time_unit = Some(TimeUnit::Seconds);
+        self
+    }
+    pub fn minutes(mut self) -> Self {
+        self.time_unit = Some(TimeUnit::Minutes);
+        self
+    }
+    pub fn hours(mut self) -> Self {
+        self.time_unit = Some(TimeUnit::Hours);
+        self
+    }
+    pub fn days(mut self) -> Self {
+        self.time_unit = Some(TimeUnit::Days);
+        self
+    }
+    pub fn week(mut self) -> Self {
+        self.time_unit = Some(TimeUnit::Weeks);
+        self
+    }
+
+    pub fn at(mut self, time_str: &str) -> Self {
+        self.at_time = Some(NaiveTime::parse_from_str(time_str, "%H:%M").unwrap());
+        self
+    }
+
+    pub fn monday(mut self) -> Self {
+        self.weekday = Some(Weekday::Mon);
+        self
+    }
+    pub fn tuesday(mut self) -> Self {
+        self.weekday = Some(Weekday::Tue);
+        self
+    }
+    pub fn wednesday(mut self) -> Self {
+        self.weekday = Some(Weekday::Wed);
+        self
+    }
+    pub fn thursday(mut self) -> Self {
+        self.weekday = Some(Weekday::Thu);
+        self
+    }
+    pub fn friday(mut self) -> Self {
+        self.weekday = Some(Weekday::Fri);
+        self
+    }
+    pub fn saturday(mut self) -> Self {
+        self.weekday = Some(Weekday::Sat);
+        self
+    }
+    pub fn sunday(mut self) -> Self {
+        self.weekday = Some(Weekday::Sun);
+        self
+    }
+
+    /// Only fires on the `n`th `weekday` of each month, e.g. `nth_weekday(2, Weekday::Fri)`
+    /// for the 2nd Friday. Without a time unit the job runs once that day.
+    ///
+    /// # Panics
+    ///
+    /// Panics unless `n` is between 1 and 5.
+    pub fn nth_weekday(mut self, n: u8, weekday: Weekday) -> Self {
+        assert!((1..=5).contains(&n), "nth_weekday expects 1 to 5, got {n}");
+        self.month_day = Some(MonthDay::NthWeekday(n, weekday));
+        self
+    }
+
+    /// Only fires on the last day of each month. Without a time unit the job
+    /// runs once that day.
+    pub fn last_day_of_month(mut self) -> Self {
+        self.month_day = Some(MonthDay::LastDay);
+        self
+    }
+
+    pub fn repeat(mut self, count: i32) -> Self {
+        self.repeat = Some(count);
+        self
+    }
+
+    /// Never runs the job before `at`.
+    pub fn starting(mut self, at: DateTime<Utc>) -> Self {
+        self.starts = Some(at);
+        self
+    }
+
+    /// Never runs the job after `at`.
+    pub fn until(mut self, at: DateTime<Utc>) -> Self {
+        self.ends = Some(at);
+        self
+    }
+
+    /// Only fires between the times of day `start` and `end`, `"HH:MM"` in
+    /// UTC, `end` excluded: an interval job resumes at `start` the next day.
+    /// A window like `("22:00", "06:00")` spans midnight.
+    pub fn between(mut self, start: &str, end: &str) -> Self {
+        self.window = Some((
+            NaiveTime::parse_from_str(start, "%H:%M").unwrap(),
+            NaiveTime::parse_from_str(end, "%H:%M").unwrap(),
+        ));
+        self
+    }
+
+    /// Skips Saturdays and Sundays.
+    pub fn except_weekends(mut self) -> Self {
+        self.except_weekends = true;
+        self
+    }
+
+    /// Aligns an interval schedule to the fixed grid `epoch + phase + k * interval`
+    /// instead of the last run, so jobs sharing an interval can be offset from
+    /// each other. Phased jobs wait for the first slot after registration.
+    pub fn phase(mut self, phase: Duration) -> Self {
+        self.phase = Some(phase);
+        self
+    }
+
+    /// Fires a sunrise or sunset job `offset` after the event, or before it if
+    /// `offset` is negative.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the job was not started with
+    /// [`at_sunrise`](crate::JobRunner::at_sunrise) or
+    /// [`at_sunset`](crate::JobRunner::at_sunset).
+    #[cfg(feature = "solar")]
+    pub fn offset(mut self, offset: Duration) -> Self {
+        let solar = self
+            .solar
+            .take()
+            .expect("offset requires a sunrise or sunset job");
+        self.solar = Some(solar.offset(offset));
+        self
+    }
+
+    /// Runs the job as soon as it is registered, even if its schedule's first
+    /// time is later, e.g. an `at` time later today. Later runs follow the
+    /// schedule.
+    pub fn run_immediately(mut self) -> Self {
+        self.first_run = Some(FirstRun::Immediately);
+        self
+    }
+
+    /// Never runs the job at registration time. The first run is the first
+    /// scheduled time after it: one full interval later for an interval job,
+    /// the next day for an `at` job whose time has already passed today.
+    pub fn defer_first_run(mut self) -> Self {
+        self.first_run = Some(FirstRun::Deferred);
+        self
+    }
+
+    /// Only executes `percent` of the scheduled occurrences until `until`; the
+    /// rest are recorded in the audit log as sampled out.
+    pub fn experiment(mut self, percent: u8, until: DateTime<Utc>) -> Self {
+        self.options.experiment = Some(Experiment::new(percent, until));
+        self
+    }
+
+    /// Also runs the job whenever `upstream` finishes successfully. A job built
+    /// without a time unit only runs after its upstream jobs.
+    pub fn after(mut self, upstream: JobHandle) -> Self {
+        self.options
+            .upstream
+            .push((upstream, RunCondition::Success));
+        self
+    }
+
+    /// Like `after`, but runs when `upstream` finishes regardless of its outcome.
+    pub fn after_completion(mut self, upstream: JobHandle) -> Self {
+        self.options
+            .upstream
+            .push((upstream, RunCondition::Completion));
+        self
+    }
+
+    /// Never runs concurrently with another job of the same group; a due job
+    /// waits until the group is free.
+    pub fn exclusive_group(mut self, group: &str) -> Self {
+        self.options.group = Some(group.to_string());
+        self
+    }
+
+    /// Takes a permit of the shared resource `name` for each run; see
+    /// [`JobRunner::resource`](crate::JobRunner::resource). Can be called
+    /// more than once to require several resources.
+    pub fn requires(mut self, name: &str) -> Self {
+        if !self.options.resources.iter().any(|held| held == name) {
+            self.options.resources.push(name.to_string());
+        }
+        self
+    }
+
+    /// Lets [`JobRunner::apply_offsets`](crate::JobRunner::apply_offsets) shift
+    /// the job when it starts in the same minute as other jobs.
+    pub fn auto_stagger(mut self) -> Self {
+        self.options.auto_stagger = true;
+        self
+    }
+
+    /// Names the job, for [`JobRunner::trigger_by_name`](crate::JobRunner::trigger_by_name)
+    /// and [`JobRunner::find_by_name`](crate::JobRunner::find_by_name). Names
+    /// should be unique; lookups return the first match.
+    pub fn name(mut self, name: &str) -> Self {
+        self.options.name = Some(name.to_string());
+        self
+    }
+
+    /// Adds the job to a set run together by
+    /// [`JobRunner::trigger_tag_ordered`](crate::JobRunner::trigger_tag_ordered).
+    /// Can be given several times.
+    pub fn tag(mut self, tag: &str) -> Self {
+        self.options.tags.push(tag.to_string());
+        self
+    }
+
+    /// Jobs due in the same tick start in descending priority order, so a
+    /// higher-priority job gets a free concurrency or group slot first.
+    /// Defaults to 0.
+    pub fn priority(mut self, priority: u8) -> Self {
+        self.options.priority = priority;
+        self
+    }
+
+    /// Tracks the share of runs that succeed over the rolling `window` and
+    /// emits [`Event::SloBreached`](crate::Event::SloBreached) when it falls
+    /// below `success_rate`, e.g. `.slo(0.99, Duration::from_secs(7 * 86_400))`.
+    /// Panics and time-outs count as failures. The rate is only judged once
+    /// the window holds [`SloStatus::MIN_RUNS`](crate::SloStatus::MIN_RUNS) runs. The
+    /// current rate is reported in [`JobInfo::slo`](crate::JobInfo::slo).
+    ///
+    /// # Panics
+    ///
+    /// Panics unless `success_rate` is between 0 and 1.
+    pub fn slo(mut self, success_rate: f64, window: std::time::Duration) -> Self {
+        self.options.slo = Some(Slo::new(success_rate, window));
+        self
+    }
+
+    /// Marks a run as [`TimedOut`](crate::JobOutcome::TimedOut) once it has
+    /// taken longer than `timeout`, freeing its concurrency and group slots.
+    /// The task's thread cannot be stopped and is left to finish detached;
+    /// its late result is discarded.
+    pub fn timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.options.timeout = Some(timeout);
+        self
+    }
+
+    /// Emits [`Event::CompletionOverdue`](crate::Event::CompletionOverdue)
+    /// when the job has not completed successfully for longer than `max_gap`,
+    /// counting from registration or its last success, so a job that has
+    /// silently stopped running is noticed. Use
+    /// [`JobRunner::watchdog`](crate::JobRunner::watchdog) for a callback.
+    pub fn expect_completion_within(mut self, max_gap: std::time::Duration) -> Self {
+        self.options.watchdog = Some(Watchdog::new(max_gap, self.client.now()));
+        self
+    }
+
+    /// Chooses whether the job store records a run before or after the task
+    /// executes; see [`Delivery`]. Defaults to at-most-once.
+    pub fn delivery(mut self, delivery: Delivery) -> Self {
+        self.options.delivery = delivery;
+        self
+    }
+
+    /// Skips scheduled runs while `flag` is disabled in the runner's flag provider.
+    pub fn gated_by(mut self, flag: &str) -> Self {
+        self.options.gate = Some(flag.to_string());
+        self
+    }
+
+    /// Disables the job after `limit` consecutive panicking runs, overriding
+    /// [`JobRunner::set_max_consecutive_panics`](crate::JobRunner::set_max_consecutive_panics).
+    pub fn disable_after_panics(mut self, limit: u32) -> Self {
+        self.options.max_consecutive_panics = Some(limit);
+        self
+    }
+
+    /// Calls `callback` once the job has no runs left and is removed from the
+    /// runner; see [`JobRunner::prune`](crate::JobRunner::prune).
+    pub fn on_complete<F: FnOnce(JobHandle) + Send + 'static>(mut self, callback: F) -> Self {
+        self.options.on_complete = Some(Box::new(callback));
+        self
+    }
+
+    /// Skips scheduled runs while `source` has not sent a
+    /// [`heartbeat`](crate::JobRunner::heartbeat) within `max_age`, e.g.
+    /// while an upstream service is down. Can be given several times.
+    pub fn requires_heartbeat(mut self, source: &str, max_age: std::time::Duration) -> Self {
+        let max_age = Duration::from_std(max_age).unwrap_or(Duration::MAX);
+        self.options.heartbeats.push((source.to_string(), max_age));
+        self
+    }
+
+    /// Only lets scheduled runs start between the times of day `not_before`
+    /// and `not_after`, `"HH:MM"` in UTC, both included. A run that would
+    /// start outside them, e.g. because it waited for a free slot or the
+    /// runner was paused, is skipped and recorded as
+    /// [`AuditAction::Missed`](crate::AuditAction::Missed); manual triggers are
+    /// not affected. A window like `("22:00", "02:00")` spans midnight.
+    pub fn occurrence_window(mut self, not_before: &str, not_after: &str) -> Self {
+        self.options.window = Some(OccurrenceWindow {
+            not_before: NaiveTime::parse_from_str(not_before, "%H:%M").unwrap(),
+            not_after: NaiveTime::parse_from_str(not_after, "%H:%M").unwrap(),
+        });
+        self
+    }
+
+    /// Runs each scheduled occurrence on only one of the runner instances
+    /// sharing the runner's lock provider; see [`LockProvider`](crate::LockProvider).
+    /// The job must also be given a [`name`](Self::name), which the instances
+    /// claim its runs under.
+    pub fn singleton(mut self) -> Self {
+        self.options.singleton = true;
+        self
+    }
+
+    pub fn do_<F>(self, job_fn: F) -> JobHandle
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        self.do_with_context(move |_| job_fn())
+    }
+
+    /// Runs `compute` on the job's schedule and publishes each result through
+    /// the returned [`CacheRefresh`], e.g. to reload a cached value every few
+    /// minutes.
+    pub fn do_cached<T, F>(self, compute: F) -> CacheRefresh<T>
+    where
+        T: Send + Sync + 'static,
+        F: Fn() -> T + Send + Sync + 'static,
+    {
+        let shared = Arc::new(Shared::new());
+        let publisher = shared.clone();
+        let job = self.do_(move || publisher.publish(compute()));
+        CacheRefresh::new(job, shared)
+    }
+
+    /// Like `do_`, but also returns a [`Completion`] that resolves when the
+    /// job's first run finishes. Meant for one-shot jobs such as
+    /// [`once_at`](crate::JobRunner::once_at) and [`after`](crate::JobRunner::after)
+    /// jobs, so the caller can wait for deferred work instead of polling the
+    /// history.
+    pub fn do_awaitable<F>(mut self, job_fn: F) -> Completion
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        let completer = Completer::default();
+        let slot = completer.slot();
+        self.options.completion = Some(completer);
+        Completion::new(self.do_(job_fn), slot)
+    }
+
+    /// Like `do_`, but the task receives the runner's application state, as set
+    /// with [`JobRunner::with_state`](crate::JobRunner::with_state).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the runner has no state of type `S`.
+    pub fn do_with_state<S, F>(self, job_fn: F) -> JobHandle
+    where
+        S: Send + Sync + 'static,
+        F: Fn(Arc<S>) + Send + Sync + 'static,
+    {
+        let state = self.client.app_state::<S>().unwrap_or_else(|| {
+            panic!(
+                "do_with_state requires runner state of type {}",
+                std::any::type_name::<S>()
+            )
+        });
+        self.do_with_context(move |_| job_fn(state.clone()))
+    }
+
+    /// Like `do_`, but the task receives the [`JobContext`] of each run.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the job is a [`singleton`](Self::singleton) without a name.
+    pub fn do_with_context<F>(self, job_fn: F) -> JobHandle
+    where
+        F: Fn(&JobContext) + Send + Sync + 'static,
+    {
+        assert!(
+            !self.options.singleton || self.options.name.is_some(),
+            "singleton jobs need a name to claim their runs under"
+        );
+        #[cfg(feature = "solar")]
+        let custom = self
+            .custom
+            .or_else(|| Some(Box::new(self.solar?) as Box<dyn Scheduler>));
+        #[cfg(not(feature = "solar"))]
+        let custom = self.custom;
+        let schedule: Box<dyn Scheduler> = match custom {
+            Some(custom) => custom,
+            None if self.time_unit.is_none() && !self.options.upstream.is_empty() => {
+                Box::new(AfterJobs {
+                    upstream: self.options.upstream.clone(),
+                })
+            }
+            None => Box::new(Schedule {
+                interval: self.interval,
+                time_unit: match self.month_day {
+                    Some(_) => self.time_unit.unwrap_or(TimeUnit::Days),
+                    None => self.time_unit.expect("TimeUnit required"),
+                },
+                at_time: self.at_time,
+                weekday: self.weekday,
+                phase: self.phase,
+                month_day: self.month_day,
+                repeat: self.repeat,
+                starts: self.starts,
+                ends: self.ends,
+                window: self.window,
+                except_weekends: self.except_weekends,
+            }),
+        };
+        let now = self.client.now();
+        let mut job = Job::new(schedule, Arc::new(job_fn), self.repeat, now);
+        match self.first_run {
+            Some(FirstRun::Immediately) => job.run_immediately(now),
+            Some(FirstRun::Deferred) => job.defer_first_run(now),
+            None => {}
+        }
+
+        self.client.add_entry(job, self.options)
+    }
+}
@@ -0,0 +1,114 @@
+use crate::event::Event;
+use std::collections::VecDeque;
+use std::sync::{Condvar, Mutex};
+use std::time::Duration;
+
+/// What [`JobRunner::set_event_queue`](crate::JobRunner::set_event_queue)
+/// does with an event when the queue is full.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Discard the oldest queued event to make room.
+    #[default]
+    DropOldest,
+    /// Discard the new event.
+    DropNewest,
+    /// Wait for the listeners to catch up. The emitting thread, which may be
+    /// the one calling `run_pending`, blocks meanwhile.
+    Block,
+}
+
+/// Events waiting for the dispatcher thread to hand them to the listeners.
+pub(crate) struct EventBus {
+    queue: Mutex<Queue>,
+    not_empty: Condvar,
+    not_full: Condvar,
+    capacity: usize,
+    policy: OverflowPolicy,
+}
+
+#[derive(Default)]
+struct Queue {
+    events: VecDeque<Event>,
+    closed: bool,
+    dropped: u64,
+    /// Publishers waiting for room under [`OverflowPolicy::Block`].
+    blocked: usize,
+}
+
+impl EventBus {
+    pub(crate) fn new(capacity: usize, policy: OverflowPolicy) -> Self {
+        EventBus {
+            queue: Mutex::default(),
+            not_empty: Condvar::new(),
+            not_full: Condvar::new(),
+            capacity: capacity.max(1),
+            policy,
+        }
+    }
+
+    pub(crate) fn publish(&self, event: Event) {
+        let mut queue = self.queue.lock().unwrap();
+        while queue.events.len() >= self.capacity && !queue.closed {
+            match self.policy {
+                OverflowPolicy::DropOldest => {
+                    queue.events.pop_front();
+                    queue.dropped += 1;
+                }
+                OverflowPolicy::DropNewest => {
+                    queue.dropped += 1;
+                    return;
+                }
+                OverflowPolicy::Block => {
+                    queue.blocked += 1;
+                    queue = self.not_full.wait(queue).unwrap();
+                    queue.blocked -= 1;
+                }
+            }
+        }
+        if queue.closed {
+            queue.dropped += 1;
+            return;
+        }
+        queue.events.push_back(event);
+        self.not_empty.notify_one();
+    }
+
+    /// The next event, waiting up to `timeout` for one.
+    pub(crate) fn next(&self, timeout: Duration) -> Option<Event> {
+        let mut queue = self.queue.lock().unwrap();
+        if queue.events.is_empty() && !queue.closed {
+            queue = self.not_empty.wait_timeout(queue, timeout).unwrap().0;
+        }
+        let event = queue.events.pop_front()?;
+        self.not_full.notify_one();
+        Some(event)
+    }
+
+    /// Nothing queued and nobody waiting to queue.
+    pub(crate) fn is_idle(&self) -> bool {
+        let queue = self.queue.lock().unwrap();
+        queue.events.is_empty() && queue.blocked == 0
+    }
+
+    /// Closed and with nothing left to deliver.
+    pub(crate) fn is_finished(&self) -> bool {
+        let queue = self.queue.lock().unwrap();
+        queue.closed && queue.events.is_empty()
+    }
+
+    /// Stops accepting events; those already queued are still delivered.
+    pub(crate) fn close(&self) {
+        self.queue.lock().unwrap().closed = true;
+        self.not_empty.notify_all();
+        self.not_full.notify_all();
+    }
+
+    #[cfg(feature = "metrics")]
+    pub(crate) fn len(&self) -> usize {
+        self.queue.lock().unwrap().events.len()
+    }
+
+    pub(crate) fn dropped(&self) -> u64 {
+        self.queue.lock().unwrap().dropped
+    }
+}
@@ -0,0 +1,66 @@
+use crate::runner::JobHandle;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+/// A value recomputed by a job registered with
+/// [`JobBuilder::do_cached`](crate::JobBuilder::do_cached). Clones share the
+/// value; readers get the latest result without waiting for a refresh in
+/// progress. A refresh that panics keeps the previous value.
+pub struct CacheRefresh<T> {
+    job: JobHandle,
+    shared: Arc<Shared<T>>,
+}
+
+pub(crate) struct Shared<T> {
+    value: Mutex<Option<Arc<T>>>,
+    published: Condvar,
+}
+
+impl<T> Shared<T> {
+    pub(crate) fn new() -> Self {
+        Shared {
+            value: Mutex::new(None),
+            published: Condvar::new(),
+        }
+    }
+
+    pub(crate) fn publish(&self, value: T) {
+        *self.value.lock().unwrap() = Some(Arc::new(value));
+        self.published.notify_all();
+    }
+}
+
+impl<T> CacheRefresh<T> {
+    pub(crate) fn new(job: JobHandle, shared: Arc<Shared<T>>) -> Self {
+        CacheRefresh { job, shared }
+    }
+
+    pub fn job(&self) -> JobHandle {
+        self.job
+    }
+
+    /// The latest value, or `None` before the first refresh has finished.
+    pub fn get(&self) -> Option<Arc<T>> {
+        self.shared.value.lock().unwrap().clone()
+    }
+
+    /// Like [`get`](Self::get), waiting up to `timeout` for the first value.
+    pub fn wait_timeout(&self, timeout: Duration) -> Option<Arc<T>> {
+        let value = self.shared.value.lock().unwrap();
+        let (value, _) = self
+            .shared
+            .published
+            .wait_timeout_while(value, timeout, |value| value.is_none())
+            .unwrap();
+        value.clone()
+    }
+}
+
+impl<T> Clone for CacheRefresh<T> {
+    fn clone(&self) -> Self {
+        CacheRefresh {
+            job: self.job,
+            shared: self.shared.clone(),
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use crate::run::JobOutcome;
+use crate::runner::JobHandle;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Context, Poll, Waker};
+
+#[derive(Default)]
+pub(crate) struct Slot {
+    state: Mutex<SlotState>,
+    ready: Condvar,
+}
+
+#[derive(Default)]
+struct SlotState {
+    /// `Some(None)` once the job can no longer finish a run.
+    outcome: Option<Option<JobOutcome>>,
+    waker: Option<Waker>,
+}
+
+/// The first run of a job registered with
+/// [`JobBuilder::do_awaitable`](crate::JobBuilder::do_awaitable). Block on it
+/// with [`wait`](Self::wait) or `.await` it; either gives the run's outcome,
+/// or `None` if the job was cancelled, skipped its only run or the runner
+/// shut down first.
+pub struct Completion {
+    job: JobHandle,
+    slot: Arc<Slot>,
+}
+
+impl Completion {
+    pub(crate) fn new(job: JobHandle, slot: Arc<Slot>) -> Self {
+        Completion { job, slot }
+    }
+
+    pub fn job(&self) -> JobHandle {
+        self.job
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.slot.state.lock().unwrap().outcome.is_some()
+    }
+
+    pub fn wait(&self) -> Option<JobOutcome> {
+        let mut state = self.slot.state.lock().unwrap();
+        loop {
+            if let Some(outcome) = &state.outcome {
+                return outcome.clone();
+            }
+            state = self.slot.ready.wait(state).unwrap();
+        }
+    }
+}
+
+impl Future for Completion {
+    type Output = Option<JobOutcome>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.slot.state.lock().unwrap();
+        match &state.outcome {
+            Some(outcome) => Poll::Ready(outcome.clone()),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// The runner's side of a [`Completion`]. Dropping it unresolved resolves the
+/// completion with `None`.
+#[derive(Default)]
+pub(crate) struct Completer(Arc<Slot>);
+
+impl Completer {
+    pub(crate) fn slot(&self) -> Arc<Slot> {
+        self.0.clone()
+    }
+
+    pub(crate) fn complete(&self, outcome: Option<JobOutcome>) {
+        let mut state = self.0.state.lock().unwrap();
+        if state.outcome.is_some() {
+            return;
+        }
+        state.outcome = Some(outcome);
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+        self.0.ready.notify_all();
+    }
+}
+
+impl Drop for Completer {
+    fn drop(&mut self) {
+        self.complete(None);
+    }
+}
@@ -0,0 +1,351 @@
+//! Job definitions loaded from a TOML file:
+//!
+//! ```toml
+//! [runner]
+//! max_concurrent_jobs = 4
+//!
+//! [resources]
+//! database = 2
+//!
+//! [[job]]
+//! name = "backup"
+//! every = "1 day"
+//! at = "02:00"
+//! requires = ["database"]
+//!
+//! [[job]]
+//! name = "upload"
+//! after = "backup"
+//! timeout = "10 minutes"
+//! ```
+//!
+//! What a job does is up to the caller of [`Config::apply`]. Unknown tables
+//! and job keys are rejected, so that typos do not go unnoticed; a caller
+//! that needs its own, such as a `command` for each job, lists them for
+//! [`Config::parse_with_extensions`] and finds them in [`Config::extra`] and
+//! [`JobConfig::extra`].
+
+use crate::parse::ParseError;
+use crate::runner::{JobHandle, JobRunner};
+use crate::schedule::Schedule;
+use chrono::NaiveTime;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Config {
+    #[serde(default)]
+    pub runner: RunnerConfig,
+    /// Permits of each shared resource, see [`JobRunner::resource`].
+    #[serde(default)]
+    pub resources: BTreeMap<String, usize>,
+    #[serde(default, rename = "job")]
+    pub jobs: Vec<JobConfig>,
+    /// Tables other than the ones above, for the caller to interpret.
+    #[serde(flatten)]
+    #[cfg_attr(feature = "schema", schemars(skip))]
+    pub extra: BTreeMap<String, toml::Value>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct RunnerConfig {
+    pub max_concurrent_jobs: Option<usize>,
+    pub history_limit: Option<usize>,
+    /// E.g. `"30 seconds"`.
+    pub trigger_dedup_window: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct JobConfig {
+    pub name: String,
+    /// Anything that follows `every` in a [`Schedule`] written as text, e.g.
+    /// `"5 minutes"`, `"5m"`, `"hour"` or
+    /// `"10 minutes between 09:00 and 17:00"`.
+    pub every: Option<String>,
+    /// Time of day, `"HH:MM"`.
+    pub at: Option<String>,
+    /// Weekday, e.g. `"monday"`.
+    pub on: Option<String>,
+    /// Name of a job defined earlier that this one runs after.
+    pub after: Option<String>,
+    pub repeat: Option<i32>,
+    /// E.g. `"90 seconds"`.
+    pub timeout: Option<String>,
+    pub group: Option<String>,
+    pub priority: Option<u8>,
+    /// Names of shared resources the job takes a permit of.
+    #[serde(default)]
+    pub requires: Vec<String>,
+    /// See [`JobBuilder::tag`](crate::JobBuilder::tag).
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Heartbeat sources the job needs, with their maximum age, e.g.
+    /// `{ ingest = "10 minutes" }`; see [`JobBuilder::requires_heartbeat`](crate::JobBuilder::requires_heartbeat).
+    #[serde(default)]
+    pub heartbeats: BTreeMap<String, String>,
+    /// Keys not used by the scheduler, for the caller to interpret.
+    #[serde(flatten)]
+    #[cfg_attr(feature = "schema", schemars(skip))]
+    pub extra: BTreeMap<String, toml::Value>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConfigError(String);
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Error for ConfigError {}
+
+impl Config {
+    /// Parses and validates a configuration.
+    pub fn parse(text: &str) -> Result<Config, ConfigError> {
+        Config::parse_with_extensions(text, &[], &[])
+    }
+
+    /// Like [`parse`](Self::parse), but also accepts the top-level tables
+    /// named in `tables` and the job keys named in `job_keys`, which end up
+    /// in [`Config::extra`] and [`JobConfig::extra`].
+    pub fn parse_with_extensions(
+        text: &str,
+        tables: &[&str],
+        job_keys: &[&str],
+    ) -> Result<Config, ConfigError> {
+        let config: Config =
+            toml::from_str(text).map_err(|error| ConfigError(error.to_string()))?;
+        if let Some(table) = config
+            .extra
+            .keys()
+            .find(|key| !tables.contains(&key.as_str()))
+        {
+            return Err(ConfigError(format!("unknown table `{table}`")));
+        }
+        for job in &config.jobs {
+            if let Some(key) = job
+                .extra
+                .keys()
+                .find(|key| !job_keys.contains(&key.as_str()))
+            {
+                return Err(ConfigError(format!(
+                    "job `{}`: unknown key `{key}`",
+                    job.name
+                )));
+            }
+        }
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// JSON Schema of the configuration file, for editor completion and for
+    /// checking job files in CI. It allows the extra tables and job keys
+    /// that [`parse_with_extensions`](Self::parse_with_extensions) can accept.
+    #[cfg(feature = "schema")]
+    pub fn json_schema() -> schemars::Schema {
+        schemars::schema_for!(Config)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Config, ConfigError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|error| ConfigError(format!("{}: {error}", path.display())))?;
+        Config::parse(&text)
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        if let Some(window) = &self.runner.trigger_dedup_window {
+            parse_duration(window)?;
+        }
+        let mut names = HashSet::new();
+        for job in &self.jobs {
+            let fail = |message: String| ConfigError(format!("job `{}`: {message}", job.name));
+            if let Some(upstream) = &job.after {
+                if !names.contains(upstream.as_str()) {
+                    return Err(fail(format!(
+                        "`after` names unknown or later job `{upstream}`"
+                    )));
+                }
+            }
+            if !names.insert(job.name.as_str()) {
+                return Err(fail("duplicate name".to_string()));
+            }
+            match &job.every {
+                Some(_) => {}
+                None if job.after.is_none() => {
+                    return Err(fail("needs `every` or `after`".to_string()));
+                }
+                None if job.at.is_some() || job.on.is_some() => {
+                    return Err(fail("`at` and `on` need `every`".to_string()));
+                }
+                None => {}
+            }
+            job.timer().map_err(|error| fail(error.0))?;
+            if let Some(timeout) = &job.timeout {
+                parse_duration(timeout).map_err(|error| fail(error.0))?;
+            }
+            for max_age in job.heartbeats.values() {
+                parse_duration(max_age).map_err(|error| fail(error.0))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies the runner settings and registers every job, in order, with
+    /// the task `task_for` builds for it. Returns the handles in the same
+    /// order, or an error without registering anything if the configuration
+    /// is invalid.
+    pub fn apply<F, T>(
+        &self,
+        runner: &JobRunner,
+        mut task_for: F,
+    ) -> Result<Vec<JobHandle>, ConfigError>
+    where
+        F: FnMut(&JobConfig) -> T,
+        T: Fn() + Send + Sync + 'static,
+    {
+        self.validate()?;
+        if let Some(limit) = self.runner.max_concurrent_jobs {
+            runner.set_max_concurrent_jobs(limit);
+        }
+        if let Some(limit) = self.runner.history_limit {
+            runner.set_history_limit(limit);
+        }
+        if let Some(window) = &self.runner.trigger_dedup_window {
+            let window = parse_duration(window).expect("validated");
+            runner.set_trigger_dedup_window(chrono::Duration::from_std(window).unwrap_or_default());
+        }
+        for (name, &permits) in &self.resources {
+            runner.resource(name, permits);
+        }
+
+        let mut handles: Vec<JobHandle> = vec![];
+        for job in &self.jobs {
+            let upstream = job.after.as_ref().map(|name| {
+                let index = self.jobs.iter().position(|job| &job.name == name);
+                handles[index.expect("validated")]
+            });
+            let builder = match (&job.every, upstream) {
+                (Some(_), _) => {
+                    runner.on_schedule(job.timer().expect("validated").expect("`every` is set"))
+                }
+                (None, Some(upstream)) => runner.after(upstream),
+                (None, None) => unreachable!("validated"),
+            };
+            let mut builder = builder.name(&job.name);
+            if let (Some(_), Some(upstream)) = (&job.every, upstream) {
+                builder = builder.after(upstream);
+            }
+            if let Some(count) = job.repeat {
+                builder = builder.repeat(count);
+            }
+            if let Some(timeout) = &job.timeout {
+                builder = builder.timeout(parse_duration(timeout).expect("validated"));
+            }
+            if let Some(group) = &job.group {
+                builder = builder.exclusive_group(group);
+            }
+            if let Some(priority) = job.priority {
+                builder = builder.priority(priority);
+            }
+            for name in &job.requires {
+                builder = builder.requires(name);
+            }
+            for tag in &job.tags {
+                builder = builder.tag(tag);
+            }
+            for (source, max_age) in &job.heartbeats {
+                builder =
+                    builder.requires_heartbeat(source, parse_duration(max_age).expect("validated"));
+            }
+            handles.push(builder.do_(task_for(job)));
+        }
+        Ok(handles)
+    }
+}
+
+impl JobConfig {
+    /// The schedule given by `every`, `at` and `on`, if the job has one.
+    fn timer(&self) -> Result<Option<Schedule>, ConfigError> {
+        let Some(every) = &self.every else {
+            return Ok(None);
+        };
+        let mut schedule: Schedule = format!("every {every}")
+            .parse()
+            .map_err(|error: ParseError| ConfigError(format!("`every`: {error}")))?;
+        if let Some(at) = &self.at {
+            let at = NaiveTime::parse_from_str(at, "%H:%M")
+                .map_err(|_| ConfigError(format!("`at` must be HH:MM, got `{at}`")))?;
+            schedule = schedule.at(at);
+        }
+        if let Some(day) = &self.on {
+            let day =
+                weekday(day).ok_or_else(|| ConfigError(format!("unknown weekday `{day}`")))?;
+            schedule = schedule.on(day);
+        }
+        Ok(Some(schedule))
+    }
+}
+
+/// Parses a length of time as written in the file, such as `"30 seconds"`,
+/// `"5m"` or `"hour"`.
+pub fn parse_duration(text: &str) -> Result<std::time::Duration, ConfigError> {
+    crate::parse::duration(text).map_err(|error| ConfigError(error.to_string()))
+}
+
+fn weekday(name: &str) -> Option<chrono::Weekday> {
+    name.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schedule::TimeUnit;
+
+    fn timer(every: &str) -> Result<Option<Schedule>, ConfigError> {
+        let config = Config::parse(&format!("[[job]]\nname = \"job\"\nevery = \"{every}\"\n"))?;
+        config.jobs[0].timer()
+    }
+
+    #[test]
+    fn every_accepts_schedule_syntax() {
+        let five_minutes = Some(Schedule::new(5, TimeUnit::Minutes));
+        assert_eq!(timer("5 minutes"), Ok(five_minutes.clone()));
+        assert_eq!(timer("5m"), Ok(five_minutes));
+        assert_eq!(timer("hour"), Ok(Some(Schedule::new(1, TimeUnit::Hours))));
+        assert!(timer("fortnight").is_err());
+    }
+
+    #[test]
+    fn durations_share_the_interval_syntax() {
+        let ninety_seconds = Ok(std::time::Duration::from_secs(90));
+        assert_eq!(parse_duration("90 seconds"), ninety_seconds);
+        assert_eq!(parse_duration("90s"), ninety_seconds);
+        assert_eq!(
+            parse_duration("2 hours"),
+            Ok(std::time::Duration::from_secs(7200))
+        );
+        assert!(parse_duration("5 minutes ago").is_err());
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        let job = "[[job]]\nname = \"job\"\nevery = \"hour\"\n";
+        assert!(Config::parse(&format!("[runer]\n{job}")).is_err());
+        assert!(Config::parse(&format!("{job}evry = \"day\"\n")).is_err());
+        assert!(Config::parse(&format!("{job}command = \"true\"\n")).is_err());
+
+        let text = format!("[daemon]\npidfile = \"x\"\n\n{job}command = \"true\"\n");
+        let config = Config::parse_with_extensions(&text, &["daemon"], &["command"]).unwrap();
+        assert!(config.extra.contains_key("daemon"));
+        assert!(config.jobs[0].extra.contains_key("command"));
+    }
+}
@@ -0,0 +1,130 @@
+//! Detaches the process from its terminal, so a runner can replace a
+//! cron-driven script without external tooling.
+//!
+//! [`JobRunner::daemonize`] covers the common case. Programs that start
+//! threads of their own, such as an admin server, call [`detach`] first and
+//! start them afterwards: only the calling thread survives the fork.
+
+use crate::runner::JobRunner;
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+
+impl JobRunner {
+    /// Forks into the background, writes the daemon's pid to `pidfile`,
+    /// redirects stdout and stderr to `log_file` and runs the jobs until
+    /// [`shutdown`](Self::shutdown). The original process exits straight away;
+    /// the pidfile is removed when the daemon stops.
+    ///
+    /// Call it before any task has run: only the calling thread survives the
+    /// fork. Fails without forking if the log file cannot be opened or
+    /// `pidfile` belongs to a process that is still alive.
+    pub fn daemonize(
+        &self,
+        pidfile: impl AsRef<Path>,
+        log_file: impl AsRef<Path>,
+    ) -> io::Result<()> {
+        let pidfile = detach(pidfile, log_file)?;
+        self.run_forever();
+        pidfile.remove()
+    }
+}
+
+/// The pidfile written by [`detach`]. Removed on drop, or explicitly with
+/// [`remove`](Self::remove) to see errors.
+#[derive(Debug)]
+pub struct PidFile(Option<PathBuf>);
+
+impl PidFile {
+    pub fn path(&self) -> &Path {
+        self.0.as_deref().expect("only taken by remove")
+    }
+
+    pub fn remove(mut self) -> io::Result<()> {
+        match self.0.take() {
+            Some(path) => fs::remove_file(path),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        if let Some(path) = self.0.take() {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// Forks into the background, writes the new pid to `pidfile` and redirects
+/// stdout and stderr to `log_file`; the original process exits. Fails without
+/// forking if the log file cannot be opened or `pidfile` belongs to a process
+/// that is still alive.
+pub fn detach(pidfile: impl AsRef<Path>, log_file: impl AsRef<Path>) -> io::Result<PidFile> {
+    let pidfile = absolute(pidfile.as_ref())?;
+    check_not_running(&pidfile)?;
+    let log = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_file)?;
+    let null = File::open("/dev/null")?;
+
+    fork_twice()?;
+    fs::write(&pidfile, format!("{}\n", std::process::id()))?;
+    let pidfile = PidFile(Some(pidfile));
+    redirect(&null, libc::STDIN_FILENO)?;
+    redirect(&log, libc::STDOUT_FILENO)?;
+    redirect(&log, libc::STDERR_FILENO)?;
+    Ok(pidfile)
+}
+
+fn absolute(path: &Path) -> io::Result<PathBuf> {
+    if path.is_absolute() {
+        Ok(path.to_path_buf())
+    } else {
+        Ok(std::env::current_dir()?.join(path))
+    }
+}
+
+fn check_not_running(pidfile: &Path) -> io::Result<()> {
+    let Ok(contents) = fs::read_to_string(pidfile) else {
+        return Ok(());
+    };
+    let alive = contents
+        .trim()
+        .parse::<libc::pid_t>()
+        .is_ok_and(|pid| unsafe { libc::kill(pid, 0) } == 0);
+    if alive {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} belongs to a running process", pidfile.display()),
+        ));
+    }
+    Ok(())
+}
+
+/// Forks twice around `setsid`, so the surviving process has no controlling
+/// terminal and cannot acquire one.
+fn fork_twice() -> io::Result<()> {
+    fork_and_exit_parent()?;
+    if unsafe { libc::setsid() } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    fork_and_exit_parent()
+}
+
+fn fork_and_exit_parent() -> io::Result<()> {
+    match unsafe { libc::fork() } {
+        -1 => Err(io::Error::last_os_error()),
+        0 => Ok(()),
+        _ => unsafe { libc::_exit(0) },
+    }
+}
+
+fn redirect(file: &File, target: libc::c_int) -> io::Result<()> {
+    if unsafe { libc::dup2(file.as_raw_fd(), target) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
@@ -0,0 +1,47 @@
+use crate::job::Scheduler;
+use crate::run::JobOutcome;
+use crate::runner::JobHandle;
+use chrono::{DateTime, Utc};
+
+/// Which upstream outcomes start a dependent job.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RunCondition {
+    Success,
+    Completion,
+}
+
+impl RunCondition {
+    pub fn matches(&self, outcome: &JobOutcome) -> bool {
+        match self {
+            RunCondition::Success => outcome.is_success(),
+            RunCondition::Completion => !matches!(outcome, JobOutcome::Skipped),
+        }
+    }
+}
+
+/// Schedule of a job that never fires by time and only runs when one of its
+/// upstream jobs finishes.
+pub(crate) struct AfterJobs {
+    pub(crate) upstream: Vec<(JobHandle, RunCondition)>,
+}
+
+impl Scheduler for AfterJobs {
+    fn first_run(&self, _now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        None
+    }
+
+    fn next_after(&self, _after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        None
+    }
+
+    fn describe(&self) -> String {
+        self.upstream
+            .iter()
+            .map(|(job, condition)| match condition {
+                RunCondition::Success => format!("after {job} succeeds"),
+                RunCondition::Completion => format!("after {job} completes"),
+            })
+            .collect::<Vec<_>>()
+            .join(" or ")
+    }
+}
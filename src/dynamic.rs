@@ -0,0 +1,96 @@
+use crate::job::{Scheduler, Task};
+use crate::run::JobContext;
+use crate::schedule::describe_every;
+use chrono::{DateTime, Duration, NaiveTime, Utc};
+use std::sync::Arc;
+
+/// Runs different tasks at different rates depending on the time of day.
+/// Each block is registered as its own job by
+/// [`SchedulerClient::add_dynamic`](crate::SchedulerClient::add_dynamic).
+#[derive(Default)]
+pub struct DynamicJob {
+    pub(crate) schedule_blocks: Vec<(TimeWindow, Task)>,
+}
+
+impl DynamicJob {
+    pub fn new() -> Self {
+        Self {
+            schedule_blocks: vec![],
+        }
+    }
+
+    pub fn between<F: Fn() + Send + Sync + 'static>(
+        mut self,
+        start: &str,
+        end: &str,
+        interval: Duration,
+        task: F,
+    ) -> Self {
+        let start_time = NaiveTime::parse_from_str(start, "%H:%M").unwrap();
+        let end_time = NaiveTime::parse_from_str(end, "%H:%M").unwrap();
+        self.schedule_blocks.push((
+            TimeWindow::new(start_time, end_time, interval),
+            Arc::new(move |_: &JobContext| task()),
+        ));
+        self
+    }
+}
+
+/// Fires every `interval` while the time of day is within `[start, end)`.
+#[derive(Clone, Debug)]
+pub struct TimeWindow {
+    start: NaiveTime,
+    end: NaiveTime,
+    interval: Duration,
+}
+
+impl TimeWindow {
+    pub fn new(start: NaiveTime, end: NaiveTime, interval: Duration) -> Self {
+        TimeWindow {
+            start,
+            end,
+            interval,
+        }
+    }
+
+    fn contains(&self, at: DateTime<Utc>) -> bool {
+        at.time() >= self.start && at.time() < self.end
+    }
+
+    fn next_start(&self, after: DateTime<Utc>) -> DateTime<Utc> {
+        let date = if after.time() < self.start {
+            after.date_naive()
+        } else {
+            after.date_naive() + Duration::days(1)
+        };
+        date.and_time(self.start).and_utc()
+    }
+}
+
+impl Scheduler for TimeWindow {
+    fn first_run(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        if self.contains(now) {
+            Some(now)
+        } else {
+            Some(self.next_start(now))
+        }
+    }
+
+    fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let candidate = after + self.interval.max(Duration::milliseconds(1));
+        if self.contains(candidate) {
+            Some(candidate)
+        } else {
+            Some(self.next_start(after))
+        }
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "{} between {} and {}",
+            describe_every(self.interval),
+            self.start.format("%H:%M"),
+            self.end.format("%H:%M")
+        )
+    }
+}
@@ -0,0 +1,54 @@
+use crate::id::RunId;
+use crate::run::RunRecord;
+use crate::runner::JobHandle;
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+
+#[derive(Clone, Debug)]
+pub enum Event {
+    RunStarted {
+        job: JobHandle,
+        run_id: RunId,
+        scheduled_at: DateTime<Utc>,
+    },
+    RunFinished(RunRecord),
+    /// Saving to the job store failed; updates are buffered in memory until it recovers.
+    StoreDegraded {
+        error: String,
+    },
+    /// The job store accepted writes again and the buffered updates were flushed.
+    /// `dropped` updates were discarded because the buffer was full.
+    StoreRecovered {
+        flushed: usize,
+        dropped: u64,
+    },
+    /// A job with a watchdog has not completed successfully within its
+    /// expected window; see [`JobBuilder::expect_completion_within`](crate::JobBuilder::expect_completion_within).
+    CompletionOverdue {
+        job: JobHandle,
+        last_success: Option<DateTime<Utc>>,
+    },
+    /// A job panicked `consecutive_panics` times in a row and will not run on
+    /// its own until re-enabled; see
+    /// [`JobRunner::set_max_consecutive_panics`](crate::JobRunner::set_max_consecutive_panics).
+    JobDisabled {
+        job: JobHandle,
+        consecutive_panics: u32,
+    },
+    /// A job's success rate fell below its [`slo`](crate::JobBuilder::slo)
+    /// target. Emitted again only after the job has met the target since.
+    SloBreached {
+        job: JobHandle,
+        success_rate: f64,
+        target: f64,
+    },
+    /// A watchdog or `on_complete` callback of `job` panicked. The panic is
+    /// contained and the tick carries on.
+    CallbackPanicked {
+        job: JobHandle,
+        callback: &'static str,
+        message: String,
+    },
+}
+
+pub(crate) type Listener = Arc<dyn Fn(&Event) + Send + Sync>;
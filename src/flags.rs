@@ -0,0 +1,10 @@
+/// Source of feature flags consulted before running jobs marked with `gated_by`.
+pub trait FlagProvider: Send + Sync {
+    fn is_enabled(&self, flag: &str) -> bool;
+}
+
+impl<F: Fn(&str) -> bool + Send + Sync> FlagProvider for F {
+    fn is_enabled(&self, flag: &str) -> bool {
+        self(flag)
+    }
+}
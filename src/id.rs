@@ -0,0 +1,84 @@
+#[cfg(feature = "serde")]
+use serde::Serialize;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use uuid::Uuid;
+
+/// Produces the identifiers the runner assigns to jobs and runs, so they can
+/// match the identifiers used elsewhere in an application.
+pub trait IdGenerator: Send + Sync {
+    fn job_id(&self) -> String;
+    fn run_id(&self) -> String;
+}
+
+/// Random UUIDv4 identifiers. The default.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct UuidGenerator;
+
+impl IdGenerator for UuidGenerator {
+    fn job_id(&self) -> String {
+        Uuid::new_v4().to_string()
+    }
+
+    fn run_id(&self) -> String {
+        Uuid::new_v4().to_string()
+    }
+}
+
+/// Process-local, increasing identifiers such as `job-1` and `run-42`.
+#[derive(Debug, Default)]
+pub struct SequentialIds {
+    jobs: AtomicU64,
+    runs: AtomicU64,
+}
+
+impl SequentialIds {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl IdGenerator for SequentialIds {
+    fn job_id(&self) -> String {
+        format!("job-{}", self.jobs.fetch_add(1, Ordering::Relaxed) + 1)
+    }
+
+    fn run_id(&self) -> String {
+        format!("run-{}", self.runs.fetch_add(1, Ordering::Relaxed) + 1)
+    }
+}
+
+/// Stable identifier of a job, assigned by the runner's [`IdGenerator`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize), serde(transparent))]
+pub struct JobId(String);
+
+/// Unique identifier of a single execution of a job.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize), serde(transparent))]
+pub struct RunId(String);
+
+macro_rules! string_id {
+    ($name:ident) => {
+        impl $name {
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl From<String> for $name {
+            fn from(id: String) -> Self {
+                $name(id)
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(&self.0)
+            }
+        }
+    };
+}
+
+string_id!(JobId);
+string_id!(RunId);
@@ -0,0 +1,136 @@
+use crate::run::JobContext;
+use crate::schedule::describe_duration;
+use chrono::{DateTime, Duration, Utc};
+use std::sync::Arc;
+
+pub type Task = Arc<dyn Fn(&JobContext) + Send + Sync>;
+
+/// Decides when a job fires. Implementations only compute times; the runner
+/// keeps track of when each job last ran.
+pub trait Scheduler: Send + Sync {
+    /// First fire time for a job registered at `now`, or `None` if it never fires.
+    fn first_run(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>>;
+    /// Next fire time strictly after a run at `after`, or `None` if the schedule is exhausted.
+    fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>>;
+    /// Human-readable description of when the job fires, e.g. "every 2 hours".
+    fn describe(&self) -> String;
+}
+
+pub struct Job {
+    pub(crate) schedule: Box<dyn Scheduler>,
+    pub(crate) task: Task,
+    pub(crate) next_run: Option<DateTime<Utc>>,
+    pub(crate) remaining_runs: Option<i32>,
+    /// Shifts every fire time of the schedule later, see
+    /// [`JobRunner::apply_offsets`](crate::JobRunner::apply_offsets).
+    pub(crate) offset: Duration,
+}
+
+impl Job {
+    pub(crate) fn new(
+        schedule: Box<dyn Scheduler>,
+        task: Task,
+        remaining_runs: Option<i32>,
+        now: DateTime<Utc>,
+    ) -> Self {
+        let next_run = match remaining_runs {
+            Some(0) => None,
+            _ => schedule.first_run(now),
+        };
+        Job {
+            schedule,
+            task,
+            next_run,
+            remaining_runs,
+            offset: Duration::zero(),
+        }
+    }
+
+    pub fn schedule(&self) -> &dyn Scheduler {
+        self.schedule.as_ref()
+    }
+
+    pub fn next_run(&self) -> Option<DateTime<Utc>> {
+        self.next_run
+    }
+
+    /// Next fire time after a run at `after`, including the job's offset.
+    pub(crate) fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let next = self.schedule.next_after(after - self.offset)?;
+        Some(next + self.offset)
+    }
+
+    /// The fire times from `now` on, as a runner ticking on time reaches them:
+    /// a run already overdue fires at `now`, and the count of remaining runs
+    /// is used up along the way.
+    pub(crate) fn upcoming(&self, now: DateTime<Utc>) -> impl Iterator<Item = DateTime<Utc>> + '_ {
+        let mut next = self.next_run;
+        let mut remaining = self.remaining_runs;
+        std::iter::from_fn(move || {
+            let at = next?.max(now);
+            if remaining.is_some_and(|count| count <= 0) {
+                return None;
+            }
+            if let Some(count) = remaining.as_mut() {
+                *count -= 1;
+            }
+            next = self.next_after(at);
+            Some(at)
+        })
+    }
+
+    /// Fires at `now` regardless of the schedule; later runs follow it as usual.
+    pub(crate) fn run_immediately(&mut self, now: DateTime<Utc>) {
+        if self.remaining_runs != Some(0) {
+            self.next_run = Some(now);
+        }
+    }
+
+    /// Skips a first run due at registration time, waiting for the next
+    /// scheduled time instead.
+    pub(crate) fn defer_first_run(&mut self, now: DateTime<Utc>) {
+        if self.is_due(now) {
+            self.next_run = self.next_after(now);
+        }
+    }
+
+    /// Moves the job's fire times `by` later than they are now.
+    pub(crate) fn shift(&mut self, by: Duration) {
+        self.offset += by;
+        self.next_run = self.next_run.map(|next_run| next_run + by);
+    }
+
+    pub(crate) fn is_due(&self, now: DateTime<Utc>) -> bool {
+        self.next_run.is_some_and(|next_run| next_run <= now)
+    }
+
+    /// Returns the task to execute and the time it was due if the job is due
+    /// at `now`, recording the run.
+    pub(crate) fn due_task(&mut self, now: DateTime<Utc>) -> Option<(Task, DateTime<Utc>)> {
+        let scheduled_at = self.next_run?;
+        if scheduled_at > now {
+            return None;
+        }
+
+        if let Some(ref mut count) = self.remaining_runs {
+            *count -= 1;
+        }
+        self.next_run = match self.remaining_runs {
+            Some(count) if count <= 0 => None,
+            _ => self.next_after(now),
+        };
+        Some((self.task.clone(), scheduled_at))
+    }
+
+    pub fn describe(&self) -> String {
+        let mut description = self.schedule.describe();
+        if self.offset > Duration::zero() {
+            description += &format!(", staggered by {}", describe_duration(self.offset));
+        }
+        match self.remaining_runs {
+            Some(1) => format!("{description}, 1 run remaining"),
+            Some(n) => format!("{description}, {n} runs remaining"),
+            None => description,
+        }
+    }
+}
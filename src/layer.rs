@@ -0,0 +1,137 @@
+use crate::id::JobId;
+use crate::run::JobContext;
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+/// Middleware around the execution of every task, added with
+/// [`JobRunner::layer`](crate::JobRunner::layer).
+///
+/// A layer runs the task, and any layers inside it, by calling `next`. It can
+/// act before and after that, e.g. to log or time the run, or return without
+/// calling `next` to skip it. Such a run is recorded as
+/// [`JobOutcome::Skipped`](crate::JobOutcome::Skipped) and is left out of
+/// the job's metrics and SLO.
+pub trait Layer: Send + Sync {
+    fn call(&self, context: &JobContext, next: &dyn Fn(&JobContext));
+
+    /// Called when a job is cancelled or pruned, to drop any state the layer
+    /// keeps for it.
+    fn job_removed(&self, _job_id: &JobId) {}
+}
+
+impl<F: Fn(&JobContext, &dyn Fn(&JobContext)) + Send + Sync> Layer for F {
+    fn call(&self, context: &JobContext, next: &dyn Fn(&JobContext)) {
+        self(context, next)
+    }
+}
+
+/// Runs `task` inside `layers`, the first layer outermost.
+pub(crate) fn run_layered(
+    layers: &[Arc<dyn Layer>],
+    context: &JobContext,
+    task: &dyn Fn(&JobContext),
+) {
+    match layers.split_first() {
+        Some((layer, inner)) => {
+            layer.call(context, &|context| run_layered(inner, context, task));
+        }
+        None => task(context),
+    }
+}
+
+/// Lets each job run at most `max_runs` times within any `per`, counted by the
+/// time runs were due, and skips the runs beyond that.
+#[derive(Debug)]
+pub struct RateLimit {
+    max_runs: usize,
+    per: Duration,
+    runs: Mutex<HashMap<JobId, VecDeque<DateTime<Utc>>>>,
+}
+
+impl RateLimit {
+    pub fn new(max_runs: usize, per: std::time::Duration) -> Self {
+        RateLimit {
+            max_runs,
+            per: Duration::from_std(per).unwrap_or(Duration::MAX),
+            runs: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl Layer for RateLimit {
+    fn call(&self, context: &JobContext, next: &dyn Fn(&JobContext)) {
+        {
+            let mut runs = self.runs.lock().unwrap();
+            let recent = runs.entry(context.job_id.clone()).or_default();
+            let cutoff = context
+                .scheduled_at
+                .checked_sub_signed(self.per)
+                .unwrap_or(DateTime::<Utc>::MIN_UTC);
+            while recent.front().is_some_and(|at| *at <= cutoff) {
+                recent.pop_front();
+            }
+            if recent.len() >= self.max_runs {
+                return;
+            }
+            recent.push_back(context.scheduled_at);
+        }
+        next(context)
+    }
+
+    fn job_removed(&self, job_id: &JobId) {
+        self.runs.lock().unwrap().remove(job_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{JobOutcome, JobRunner, MockClock};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Lets the test inspect the limiter after handing it to the runner.
+    struct Shared(Arc<RateLimit>);
+
+    impl Layer for Shared {
+        fn call(&self, context: &JobContext, next: &dyn Fn(&JobContext)) {
+            self.0.call(context, next)
+        }
+
+        fn job_removed(&self, job_id: &JobId) {
+            self.0.job_removed(job_id)
+        }
+    }
+
+    #[test]
+    fn rate_limited_runs_are_skipped() {
+        let runner = JobRunner::with_clock(MockClock::new(Utc::now()));
+        let limit = Arc::new(RateLimit::new(1, std::time::Duration::from_secs(3600)));
+        runner.layer(Shared(Arc::clone(&limit)));
+        let runs = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&runs);
+        let job = runner.every(1).minutes().do_(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        runner.advance(Duration::minutes(2));
+
+        let outcomes: Vec<_> = runner
+            .history_for(job)
+            .into_iter()
+            .map(|record| record.outcome)
+            .collect();
+        assert_eq!(
+            outcomes,
+            [
+                JobOutcome::Success,
+                JobOutcome::Skipped,
+                JobOutcome::Skipped
+            ]
+        );
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+        assert_eq!(limit.runs.lock().unwrap().len(), 1);
+
+        assert!(runner.cancel(job));
+        assert!(limit.runs.lock().unwrap().is_empty());
+    }
+}
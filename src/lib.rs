@@ -0,0 +1,69 @@
+mod audit;
+mod builder;
+mod bus;
+mod cache;
+mod clock;
+mod completion;
+#[cfg(feature = "config")]
+pub mod config;
+#[cfg(all(unix, feature = "daemon"))]
+pub mod daemon;
+mod dependency;
+mod dynamic;
+mod event;
+mod flags;
+mod id;
+mod job;
+mod layer;
+mod load;
+mod lock;
+mod maintenance;
+#[cfg(feature = "metrics")]
+mod metrics;
+mod parse;
+mod run;
+mod runner;
+mod schedule;
+#[cfg(all(windows, feature = "windows-service"))]
+pub mod service;
+mod slo;
+#[cfg(feature = "solar")]
+mod solar;
+mod source;
+mod stagger;
+mod store;
+#[cfg(feature = "testkit")]
+pub mod testkit;
+
+pub use audit::{AuditAction, AuditEntry, TriggerMode};
+pub use builder::JobBuilder;
+pub use bus::OverflowPolicy;
+pub use cache::CacheRefresh;
+pub use clock::{Clock, MockClock, SystemClock};
+pub use completion::Completion;
+pub use dependency::RunCondition;
+pub use dynamic::{DynamicJob, TimeWindow};
+pub use event::Event;
+pub use flags::FlagProvider;
+pub use id::{IdGenerator, JobId, RunId, SequentialIds, UuidGenerator};
+pub use job::{Job, Scheduler, Task};
+pub use layer::{Layer, RateLimit};
+pub use load::{LoadBucket, LoadProfile};
+#[cfg(feature = "file-lock")]
+pub use lock::FileLocks;
+pub use lock::LockProvider;
+pub use maintenance::{CompactionReport, Retention};
+#[cfg(feature = "metrics")]
+pub use metrics::{Histogram, JobMetrics, MetricsSnapshot, RuntimeMetrics};
+pub use parse::ParseError;
+pub use run::{JobContext, JobOutcome, RunRecord};
+pub use runner::{
+    JobHandle, JobInfo, JobRunner, SchedulerClient, ShutdownReport, TagReport, TickReport,
+};
+pub use schedule::{MonthDay, Schedule, TimeUnit};
+pub use slo::SloStatus;
+#[cfg(feature = "solar")]
+pub use solar::{Solar, SolarEvent};
+pub use source::{EventSource, Trigger};
+pub use stagger::OffsetSuggestion;
+pub use store::{Delivery, JobState, JobStore, MemoryStore, StoreError, StoreUpdate};
@@ -0,0 +1,131 @@
+use crate::job::Job;
+use chrono::{DateTime, Duration, DurationRound, Utc};
+
+/// Per-job cap on simulated runs, so sub-second schedules over long horizons
+/// stay cheap to profile.
+pub(crate) const MAX_SIMULATED_RUNS: usize = 100_000;
+
+/// Expected activity in one bucket of a [`LoadProfile`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct LoadBucket {
+    pub start: DateTime<Utc>,
+    pub expected_runs: usize,
+    /// Average number of tasks running at once during the bucket, from each
+    /// job's mean historical run duration, counting the part of every run
+    /// that overlaps the bucket. Jobs that never ran count as zero.
+    pub estimated_concurrency: f64,
+}
+
+/// Forecast of scheduled runs in fixed-width buckets, hourly as returned by
+/// [`JobRunner::load_profile`](crate::JobRunner::load_profile).
+#[derive(Clone, Debug, PartialEq)]
+pub struct LoadProfile {
+    pub buckets: Vec<LoadBucket>,
+}
+
+impl LoadProfile {
+    /// Simulates each job's schedule from `now` until `now + horizon`. Runs
+    /// are counted in the bucket they start in, and their busy time in every
+    /// bucket they overlap; jobs that only run after other jobs or on manual
+    /// triggers are not forecast.
+    pub(crate) fn forecast<'a>(
+        now: DateTime<Utc>,
+        horizon: Duration,
+        bucket: Duration,
+        jobs: impl Iterator<Item = (&'a Job, Option<Duration>)>,
+    ) -> Self {
+        let first = now.duration_trunc(bucket).unwrap_or(now);
+        let end = now + horizon;
+        let mut buckets = vec![];
+        let mut start = first;
+        while start < end {
+            buckets.push(LoadBucket {
+                start,
+                expected_runs: 0,
+                estimated_concurrency: 0.0,
+            });
+            start += bucket;
+        }
+
+        let bucket_millis = bucket.num_milliseconds();
+        for (job, mean_duration) in jobs {
+            let busy = mean_duration.map_or(0, |duration| duration.num_milliseconds().max(0));
+            let runs = job.upcoming(now).take(MAX_SIMULATED_RUNS);
+            for at in runs.take_while(|at| *at < end) {
+                let offset = (at - first).num_milliseconds();
+                let index = (offset / bucket_millis) as usize;
+                if let Some(slot) = buckets.get_mut(index) {
+                    slot.expected_runs += 1;
+                }
+                // Spread the run's busy time over the buckets it spans.
+                let (mut from, until) = (offset, offset + busy);
+                for slot in buckets.iter_mut().skip(index) {
+                    if from >= until {
+                        break;
+                    }
+                    let slot_end = (from / bucket_millis + 1) * bucket_millis;
+                    let overlap = until.min(slot_end) - from;
+                    slot.estimated_concurrency += overlap as f64 / bucket_millis as f64;
+                    from = slot_end;
+                }
+            }
+        }
+        LoadProfile { buckets }
+    }
+
+    /// The bucket containing `at`, if it is within the forecast.
+    pub(crate) fn bucket_of(&self, at: DateTime<Utc>) -> Option<usize> {
+        let first = self.buckets.first()?.start;
+        let width = self
+            .buckets
+            .get(1)
+            .map_or(Duration::MAX, |next| next.start - first);
+        let index = (at - first)
+            .num_milliseconds()
+            .checked_div(width.num_milliseconds())?;
+        (at >= first && (index as usize) < self.buckets.len()).then_some(index as usize)
+    }
+
+    /// The bucket with the highest estimated concurrency, then the most runs.
+    pub fn busiest(&self) -> Option<&LoadBucket> {
+        self.buckets.iter().max_by(|a, b| {
+            a.estimated_concurrency
+                .total_cmp(&b.estimated_concurrency)
+                .then(a.expected_runs.cmp(&b.expected_runs))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schedule::{Schedule, TimeUnit};
+    use chrono::TimeZone;
+    use std::sync::Arc;
+
+    #[test]
+    fn long_runs_load_every_bucket_they_overlap() {
+        let now = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let schedule =
+            Schedule::new(1, TimeUnit::Days).at(chrono::NaiveTime::from_hms_opt(0, 30, 0).unwrap());
+        let job = Job::new(Box::new(schedule), Arc::new(|_: &_| {}), None, now);
+        let profile = LoadProfile::forecast(
+            now,
+            Duration::hours(4),
+            Duration::hours(1),
+            [(&job, Some(Duration::minutes(150)))].into_iter(),
+        );
+        let runs: Vec<_> = profile
+            .buckets
+            .iter()
+            .map(|bucket| bucket.expected_runs)
+            .collect();
+        let load: Vec<_> = profile
+            .buckets
+            .iter()
+            .map(|bucket| bucket.estimated_concurrency)
+            .collect();
+        assert_eq!(runs, [1, 0, 0, 0]);
+        assert_eq!(load, [0.5, 1.0, 1.0, 0.0]);
+    }
+}
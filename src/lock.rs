@@ -0,0 +1,93 @@
+use chrono::{DateTime, Utc};
+#[cfg(feature = "file-lock")]
+use std::path::PathBuf;
+
+/// Shared claim on scheduled runs, consulted before running jobs marked with
+/// [`singleton`](crate::JobBuilder::singleton) so that only one of several
+/// runner instances executes each occurrence.
+///
+/// Claims are keyed by the job's [`name`](crate::JobBuilder::name), which
+/// singleton jobs must have, since job ids are usually generated per process.
+/// Occurrences are told apart by the time they were due, so singleton jobs
+/// need schedules that fire at the same instants on every instance, such as
+/// `at` times, weekdays or a [`phase`](crate::JobBuilder::phase).
+pub trait LockProvider: Send + Sync {
+    /// Claims the run of the job named `job` due at `scheduled_at`. Returns
+    /// `false` if another instance already claimed it or the claim could not
+    /// be made.
+    fn try_acquire(&self, job: &str, scheduled_at: DateTime<Utc>) -> bool;
+}
+
+impl<F: Fn(&str, DateTime<Utc>) -> bool + Send + Sync> LockProvider for F {
+    fn try_acquire(&self, job: &str, scheduled_at: DateTime<Utc>) -> bool {
+        self(job, scheduled_at)
+    }
+}
+
+/// Claims runs by creating one file per occurrence in a directory shared by
+/// all instances, e.g. on a network filesystem. Claims older than a day are
+/// removed as new ones are made.
+#[cfg(feature = "file-lock")]
+#[derive(Clone, Debug)]
+pub struct FileLocks {
+    dir: PathBuf,
+}
+
+#[cfg(feature = "file-lock")]
+impl FileLocks {
+    /// Creates `dir` if it does not exist yet.
+    pub fn new(dir: impl Into<PathBuf>) -> std::io::Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        Ok(FileLocks { dir })
+    }
+
+    fn remove_stale(&self) {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return;
+        };
+        let day = std::time::Duration::from_secs(86_400);
+        for entry in entries.flatten() {
+            let stale = entry
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .is_ok_and(|modified| modified.elapsed().is_ok_and(|age| age > day));
+            if stale {
+                let _ = std::fs::remove_file(entry.path());
+            }
+        }
+    }
+}
+
+#[cfg(feature = "file-lock")]
+impl LockProvider for FileLocks {
+    fn try_acquire(&self, job: &str, scheduled_at: DateTime<Utc>) -> bool {
+        use std::io::Write;
+
+        self.remove_stale();
+        let name: String = job
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        let path = self
+            .dir
+            .join(format!("{name}.{}.lock", scheduled_at.timestamp_millis()));
+        match std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)
+        {
+            Ok(mut file) => {
+                let _ = writeln!(file, "{}", std::process::id());
+                true
+            }
+            Err(_) => false,
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use chrono::Duration;
+
+/// How long the runner keeps what it no longer needs, as applied by
+/// [`JobRunner::compact`](crate::JobRunner::compact) and the job
+/// [`JobRunner::enable_self_maintenance`](crate::JobRunner::enable_self_maintenance)
+/// registers.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Retention {
+    /// How often the maintenance job runs.
+    pub interval: std::time::Duration,
+    /// Finished runs older than this are dropped from the history.
+    pub history: Duration,
+    /// Audit entries older than this are dropped.
+    pub audit_log: Duration,
+    /// Jobs with no runs left are removed once they have been idle this long.
+    /// Only matters with [`set_auto_prune`](crate::JobRunner::set_auto_prune)
+    /// off, as the runner otherwise removes them right away.
+    pub finished_jobs: Duration,
+}
+
+impl Default for Retention {
+    fn default() -> Self {
+        Retention {
+            interval: std::time::Duration::from_secs(3600),
+            history: Duration::days(7),
+            audit_log: Duration::days(7),
+            finished_jobs: Duration::days(1),
+        }
+    }
+}
+
+/// What one pass of [`JobRunner::compact`](crate::JobRunner::compact) removed.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CompactionReport {
+    pub jobs_removed: usize,
+    pub history_removed: usize,
+    pub audit_entries_removed: usize,
+}
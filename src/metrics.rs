@@ -0,0 +1,155 @@
+use crate::id::JobId;
+use crate::run::RunRecord;
+use crate::runner::JobHandle;
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{BTreeMap, HashMap};
+
+/// Upper bounds, in seconds, of the buckets for job timings.
+const JOB_BUCKETS: [f64; 12] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 60.0,
+];
+
+/// Upper bounds, in seconds, of the buckets for the scheduler's own overhead.
+const RUNTIME_BUCKETS: [f64; 10] = [
+    0.000_01, 0.000_025, 0.000_05, 0.000_1, 0.000_25, 0.000_5, 0.001, 0.005, 0.01, 0.1,
+];
+
+/// Observations bucketed by upper bound, Prometheus style: `counts[i]` is the
+/// number of observations `<= bounds[i]`, with a final `+Inf` bucket.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Histogram {
+    pub bounds: Vec<f64>,
+    pub counts: Vec<u64>,
+    pub sum: f64,
+    pub count: u64,
+}
+
+impl Histogram {
+    fn new(bounds: &[f64]) -> Self {
+        Histogram {
+            bounds: bounds.to_vec(),
+            counts: vec![0; bounds.len() + 1],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    pub(crate) fn observe(&mut self, seconds: f64) {
+        for (bound, count) in self.bounds.iter().zip(&mut self.counts) {
+            if seconds <= *bound {
+                *count += 1;
+            }
+        }
+        self.counts[self.bounds.len()] += 1;
+        self.sum += seconds;
+        self.count += 1;
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct JobMetrics {
+    pub job: JobHandle,
+    pub job_id: JobId,
+    pub runs_total: u64,
+    pub failures_total: u64,
+    /// Time from start to finish of each run.
+    pub run_duration: Histogram,
+    /// Time between when a run was due and when it actually started.
+    pub schedule_lag: Histogram,
+    pub last_started_at: Option<DateTime<Utc>>,
+    pub next_run: Option<DateTime<Utc>>,
+}
+
+impl JobMetrics {
+    pub(crate) fn new(job: JobHandle, job_id: JobId) -> Self {
+        JobMetrics {
+            job,
+            job_id,
+            runs_total: 0,
+            failures_total: 0,
+            run_duration: Histogram::new(&JOB_BUCKETS),
+            schedule_lag: Histogram::new(&JOB_BUCKETS),
+            last_started_at: None,
+            next_run: None,
+        }
+    }
+
+    pub(crate) fn record(&mut self, record: &RunRecord) {
+        self.runs_total += 1;
+        if !record.outcome.is_success() {
+            self.failures_total += 1;
+        }
+        self.run_duration
+            .observe(seconds(record.finished_at - record.started_at));
+        self.schedule_lag
+            .observe(seconds(record.started_at - record.scheduled_at));
+        self.last_started_at = Some(record.started_at);
+    }
+}
+
+/// Health of the scheduler itself, separate from the jobs it runs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RuntimeMetrics {
+    /// Wall time of each `run_pending` tick, not counting the tasks it starts.
+    pub tick_duration: Histogram,
+    /// Wall time spent delivering each event to all listeners.
+    pub listener_latency: Histogram,
+    /// Events waiting in the event queue, see
+    /// [`JobRunner::set_event_queue`](crate::JobRunner::set_event_queue).
+    pub queued_events: usize,
+    /// Events the event queue discarded because it was full.
+    pub dropped_events: u64,
+    pub registered_jobs: usize,
+    pub history_len: usize,
+    pub audit_log_len: usize,
+    /// Updates waiting for the job store to recover.
+    pub store_buffer_len: usize,
+    /// Triggered and dependent runs waiting for the next tick.
+    pub pending_runs: usize,
+    /// Jobs that are due or requested, by exclusive group; ungrouped jobs are
+    /// counted under `""`.
+    pub queue_depths: BTreeMap<String, usize>,
+    pub running_tasks: usize,
+    /// `running_tasks` as a fraction of the concurrency limit, if one is set.
+    pub worker_utilization: Option<f64>,
+}
+
+/// Per-job counters and histograms plus the scheduler's own health, as
+/// returned by [`JobRunner::metrics_snapshot`](crate::JobRunner::metrics_snapshot).
+#[derive(Clone, Debug, PartialEq)]
+pub struct MetricsSnapshot {
+    pub at: DateTime<Utc>,
+    pub jobs: Vec<JobMetrics>,
+    pub runtime: RuntimeMetrics,
+}
+
+impl MetricsSnapshot {
+    /// Jobs whose next run was due more than `tolerance` before the snapshot
+    /// was taken, i.e. jobs that have not run on time.
+    pub fn overdue(&self, tolerance: Duration) -> Vec<&JobMetrics> {
+        self.jobs
+            .iter()
+            .filter(|job| job.next_run.is_some_and(|next| next + tolerance < self.at))
+            .collect()
+    }
+}
+
+pub(crate) struct Metrics {
+    pub(crate) jobs: HashMap<JobHandle, JobMetrics>,
+    pub(crate) tick_duration: Histogram,
+    pub(crate) listener_latency: Histogram,
+}
+
+impl Metrics {
+    pub(crate) fn new() -> Self {
+        Metrics {
+            jobs: HashMap::new(),
+            tick_duration: Histogram::new(&RUNTIME_BUCKETS),
+            listener_latency: Histogram::new(&RUNTIME_BUCKETS),
+        }
+    }
+}
+
+fn seconds(duration: Duration) -> f64 {
+    duration.num_microseconds().unwrap_or(i64::MAX).max(0) as f64 / 1e6
+}
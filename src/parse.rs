@@ -0,0 +1,321 @@
+//! Schedules written as text, such as `"every 5 minutes"`, `"daily at 09:00"`,
+//! `"every monday at 08:30 until 2025-06-30"` or
+//! `"every 10 minutes between 09:00 and 17:00 except weekends"`; times may
+//! include seconds, as in `"daily at 10:00:30"`. Anything [`Schedule`]'s
+//! `Display` produces parses back to the same schedule, except that `Display`
+//! leaves out the repeat count (`"3 times"`).
+
+use crate::schedule::{MonthDay, Schedule, TimeUnit};
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc, Weekday};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError(String);
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Error for ParseError {}
+
+fn error(message: impl Into<String>) -> ParseError {
+    ParseError(message.into())
+}
+
+impl FromStr for Schedule {
+    type Err = ParseError;
+
+    fn from_str(text: &str) -> Result<Schedule, ParseError> {
+        let text = text.to_ascii_lowercase();
+        let mut words = Words {
+            words: text.split_whitespace().collect(),
+            next: 0,
+        };
+        let mut monthly = false;
+        let mut schedule = match words.next() {
+            Some("every") => every(&mut words)?,
+            Some("hourly") => Schedule::new(1, TimeUnit::Hours),
+            Some("daily") => Schedule::new(1, TimeUnit::Days),
+            Some("weekly") => Schedule::new(1, TimeUnit::Weeks),
+            Some("monthly") => {
+                monthly = true;
+                Schedule::new(1, TimeUnit::Days)
+            }
+            _ => {
+                return Err(error(
+                    "expected `every`, `hourly`, `daily`, `weekly` or `monthly`",
+                ))
+            }
+        };
+
+        while let Some(word) = words.next() {
+            match word {
+                "at" => schedule.at_time = Some(time(words.expect("a time")?)?),
+                "on" => on(&mut words, &mut schedule)?,
+                "between" => {
+                    let start = time(words.expect("a time")?)?;
+                    words.expect_word("and")?;
+                    let end = time(words.expect("a time")?)?;
+                    schedule.window = Some((start, end));
+                }
+                "except" => {
+                    words.expect_word("weekends")?;
+                    schedule.except_weekends = true;
+                }
+                "offset" => {
+                    words.expect_word("by")?;
+                    let (count, unit) = count_and_unit(&mut words)?;
+                    schedule.phase = Some(Schedule::new(count, unit).interval_duration());
+                }
+                "from" | "starting" => schedule.starts = Some(instant(&mut words)?),
+                "until" => schedule.ends = Some(instant(&mut words)?),
+                count if words.peek() == Some("times") => {
+                    words.next();
+                    schedule.repeat = Some(
+                        count
+                            .parse()
+                            .map_err(|_| error(format!("expected a count, got `{count}`")))?,
+                    );
+                }
+                other => return Err(error(format!("unexpected `{other}`"))),
+            }
+        }
+        if monthly && schedule.month_day.is_none() {
+            return Err(error(
+                "`monthly` needs a day, e.g. `monthly on the last day of the month`",
+            ));
+        }
+        Ok(schedule)
+    }
+}
+
+/// Parses an interval on its own, such as `"5 minutes"`, `"5m"` or `"hour"`.
+#[cfg(feature = "config")]
+pub(crate) fn interval(text: &str) -> Result<(u64, TimeUnit), ParseError> {
+    let text = text.to_ascii_lowercase();
+    let mut words = Words {
+        words: text.split_whitespace().collect(),
+        next: 0,
+    };
+    let interval = count_and_unit(&mut words)?;
+    match words.next() {
+        Some(other) => Err(error(format!("unexpected `{other}`"))),
+        None => Ok(interval),
+    }
+}
+
+/// Parses a length of time written like an [`interval`], e.g. `"90 seconds"`.
+#[cfg(feature = "config")]
+pub(crate) fn duration(text: &str) -> Result<std::time::Duration, ParseError> {
+    let (count, unit) = interval(text)?;
+    let millis = match unit {
+        TimeUnit::Milliseconds => 1,
+        TimeUnit::Seconds => 1_000,
+        TimeUnit::Minutes => 60_000,
+        TimeUnit::Hours => 3_600_000,
+        TimeUnit::Days => 86_400_000,
+        TimeUnit::Weeks => 604_800_000,
+    };
+    Ok(std::time::Duration::from_millis(
+        count.saturating_mul(millis),
+    ))
+}
+
+struct Words<'a> {
+    words: Vec<&'a str>,
+    next: usize,
+}
+
+impl<'a> Words<'a> {
+    fn next(&mut self) -> Option<&'a str> {
+        let word = self.peek()?;
+        self.next += 1;
+        Some(word)
+    }
+
+    fn peek(&self) -> Option<&'a str> {
+        self.words.get(self.next).copied()
+    }
+
+    fn expect(&mut self, what: &str) -> Result<&'a str, ParseError> {
+        self.next()
+            .ok_or_else(|| error(format!("expected {what} at the end")))
+    }
+
+    fn expect_word(&mut self, word: &str) -> Result<(), ParseError> {
+        match self.next() {
+            Some(next) if next == word => Ok(()),
+            Some(next) => Err(error(format!("expected `{word}`, got `{next}`"))),
+            None => Err(error(format!("expected `{word}` at the end"))),
+        }
+    }
+}
+
+/// `5 minutes`, `5m`, `hour` or a weekday, after `every`.
+fn every(words: &mut Words) -> Result<Schedule, ParseError> {
+    if let Some(day) = words.peek().and_then(weekday) {
+        words.next();
+        return Ok(Schedule::new(1, TimeUnit::Weeks).on(day));
+    }
+    let (count, unit) = count_and_unit(words)?;
+    Ok(Schedule::new(count, unit))
+}
+
+fn count_and_unit(words: &mut Words) -> Result<(u64, TimeUnit), ParseError> {
+    let word = words.expect("an interval")?;
+    if let Ok(count) = word.parse() {
+        return Ok((count, unit(words.expect("a unit")?)?));
+    }
+    // `5m`, `90s`.
+    let digits = word
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(word.len());
+    match word[..digits].parse() {
+        Ok(count) => Ok((count, unit(&word[digits..])?)),
+        Err(_) => Ok((1, unit(word)?)),
+    }
+}
+
+fn unit(word: &str) -> Result<TimeUnit, ParseError> {
+    match word {
+        "ms" | "millisecond" | "milliseconds" => Ok(TimeUnit::Milliseconds),
+        "s" | "sec" | "secs" | "second" | "seconds" => Ok(TimeUnit::Seconds),
+        "m" | "min" | "mins" | "minute" | "minutes" => Ok(TimeUnit::Minutes),
+        "h" | "hr" | "hrs" | "hour" | "hours" => Ok(TimeUnit::Hours),
+        "d" | "day" | "days" => Ok(TimeUnit::Days),
+        "w" | "week" | "weeks" => Ok(TimeUnit::Weeks),
+        _ => Err(error(format!("unknown unit `{word}`"))),
+    }
+}
+
+/// `on mondays`, `on the last day of the month` or `on the 2nd friday`.
+fn on(words: &mut Words, schedule: &mut Schedule) -> Result<(), ParseError> {
+    let word = words.expect("a day")?;
+    if let Some(day) = weekday(word) {
+        schedule.weekday = Some(day);
+        return Ok(());
+    }
+    if word != "the" {
+        return Err(error(format!("expected a weekday, got `{word}`")));
+    }
+    let which = words.expect("a day")?;
+    schedule.month_day = Some(if which == "last" {
+        words.expect_word("day")?;
+        MonthDay::LastDay
+    } else {
+        let n = ordinal(which)?;
+        let day = words.expect("a weekday")?;
+        let day = weekday(day).ok_or_else(|| error(format!("expected a weekday, got `{day}`")))?;
+        MonthDay::NthWeekday(n, day)
+    });
+    if words.peek() == Some("of") {
+        words.next();
+        words.expect_word("the")?;
+        words.expect_word("month")?;
+    }
+    Ok(())
+}
+
+fn ordinal(word: &str) -> Result<u8, ParseError> {
+    match word {
+        "1st" | "first" => Ok(1),
+        "2nd" | "second" => Ok(2),
+        "3rd" | "third" => Ok(3),
+        "4th" | "fourth" => Ok(4),
+        "5th" | "fifth" => Ok(5),
+        _ => Err(error(format!(
+            "expected `last` or 1st to 5th, got `{word}`"
+        ))),
+    }
+}
+
+/// Accepts `monday`, `mon` and `mondays`.
+fn weekday(word: &str) -> Option<Weekday> {
+    word.parse()
+        .ok()
+        .or_else(|| word.strip_suffix('s')?.parse().ok())
+}
+
+/// `HH:MM`, `HH:MM:SS` or `HH:MM:SS.fff`.
+fn time(word: &str) -> Result<NaiveTime, ParseError> {
+    NaiveTime::parse_from_str(word, "%H:%M")
+        .or_else(|_| NaiveTime::parse_from_str(word, "%H:%M:%S%.f"))
+        .map_err(|_| error(format!("expected a time as HH:MM, got `{word}`")))
+}
+
+/// `2025-01-01`, `2025-01-01 08:30` or an RFC 3339 timestamp, in UTC.
+fn instant(words: &mut Words) -> Result<DateTime<Utc>, ParseError> {
+    let word = words.expect("a date")?;
+    if let Ok(at) = DateTime::parse_from_rfc3339(word) {
+        return Ok(at.with_timezone(&Utc));
+    }
+    let date = NaiveDate::parse_from_str(word, "%Y-%m-%d")
+        .map_err(|_| error(format!("expected a date as YYYY-MM-DD, got `{word}`")))?;
+    let at = match words.peek().map(time) {
+        Some(Ok(at)) => {
+            words.next();
+            at
+        }
+        _ => NaiveTime::MIN,
+    };
+    Ok(date.and_time(at).and_utc())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn time(hour: u32, minute: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn display_parses_back() {
+        let schedules = [
+            Schedule::new(5, TimeUnit::Minutes),
+            Schedule::new(1, TimeUnit::Hours),
+            Schedule::new(1, TimeUnit::Days).at(time(9, 0)),
+            Schedule::new(1, TimeUnit::Days).at(NaiveTime::from_hms_opt(10, 0, 30).unwrap()),
+            Schedule::new(1, TimeUnit::Minutes)
+                .between(
+                    NaiveTime::from_hms_milli_opt(9, 0, 0, 250).unwrap(),
+                    time(17, 0),
+                )
+                .starting(
+                    Utc.with_ymd_and_hms(2025, 1, 1, 8, 30, 15).unwrap()
+                        + chrono::Duration::milliseconds(500),
+                ),
+            Schedule::new(1, TimeUnit::Weeks)
+                .on(Weekday::Mon)
+                .at(time(8, 30)),
+            Schedule::new(1, TimeUnit::Days).on_month_day(MonthDay::LastDay),
+            Schedule::new(1, TimeUnit::Days).on_month_day(MonthDay::NthWeekday(2, Weekday::Fri)),
+            Schedule::new(10, TimeUnit::Minutes)
+                .between(time(9, 0), time(17, 0))
+                .except_weekends(),
+            Schedule::new(1, TimeUnit::Hours).between(time(22, 0), time(6, 0)),
+            Schedule::new(15, TimeUnit::Minutes)
+                .phase(chrono::Duration::minutes(5))
+                .starting(Utc.with_ymd_and_hms(2025, 1, 1, 8, 30, 0).unwrap())
+                .until(Utc.with_ymd_and_hms(2025, 6, 30, 0, 0, 0).unwrap()),
+        ];
+        for schedule in schedules {
+            let text = schedule.to_string();
+            assert_eq!(text.parse(), Ok(schedule), "{text}");
+        }
+    }
+
+    #[test]
+    fn window_clauses_need_their_words() {
+        assert!("every hour between 09:00".parse::<Schedule>().is_err());
+        assert!("every hour between 09:00 to 17:00"
+            .parse::<Schedule>()
+            .is_err());
+        assert!("every hour except mondays".parse::<Schedule>().is_err());
+    }
+}
@@ -0,0 +1,53 @@
+use crate::id::{JobId, RunId};
+use crate::runner::JobHandle;
+use chrono::{DateTime, Utc};
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+/// Passed to tasks registered with `do_with_context`.
+#[derive(Clone, Debug)]
+pub struct JobContext {
+    pub job: JobHandle,
+    pub job_id: JobId,
+    pub run_id: RunId,
+    /// When the run was due, or when it was triggered for manual runs.
+    pub scheduled_at: DateTime<Utc>,
+    /// Counts every run of the job, scheduled or manual, starting at 1.
+    pub run_number: u64,
+}
+
+/// Serialized as e.g. `{"status": "panicked", "message": "..."}`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize),
+    serde(tag = "status", content = "message", rename_all = "snake_case")
+)]
+pub enum JobOutcome {
+    Success,
+    Panicked(String),
+    /// The task exceeded the job's timeout. Its thread may still be running.
+    TimedOut,
+    /// A [`Layer`](crate::Layer) returned without running the task, e.g. a
+    /// [`RateLimit`](crate::RateLimit) over its limit.
+    Skipped,
+}
+
+impl JobOutcome {
+    pub fn is_success(&self) -> bool {
+        matches!(self, JobOutcome::Success)
+    }
+}
+
+/// A finished run, as kept in the runner's history.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct RunRecord {
+    pub job: JobHandle,
+    pub job_id: JobId,
+    pub run_id: RunId,
+    pub scheduled_at: DateTime<Utc>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub outcome: JobOutcome,
+}
@@ -0,0 +1,3319 @@
+use crate::audit::{AuditAction, AuditEntry, TriggerMode};
+use crate::builder::JobBuilder;
+use crate::bus::{EventBus, OverflowPolicy};
+use crate::clock::{Clock, MockClock, SystemClock};
+use crate::completion::{Completer, Completion};
+use crate::dependency::{AfterJobs, RunCondition};
+use crate::dynamic::DynamicJob;
+use crate::event::{Event, Listener};
+use crate::flags::FlagProvider;
+use crate::id::{IdGenerator, JobId, RunId, UuidGenerator};
+use crate::job::{Job, Scheduler, Task};
+use crate::layer::{run_layered, Layer};
+use crate::load::{LoadProfile, MAX_SIMULATED_RUNS};
+use crate::lock::LockProvider;
+use crate::maintenance::{CompactionReport, Retention};
+#[cfg(feature = "metrics")]
+use crate::metrics::{JobMetrics, Metrics, MetricsSnapshot, RuntimeMetrics};
+use crate::parse::ParseError;
+use crate::run::{JobContext, JobOutcome, RunRecord};
+use crate::schedule::{describe_duration, OnceAt, Schedule};
+use crate::slo::{Slo, SloStatus};
+#[cfg(feature = "solar")]
+use crate::solar::{Solar, SolarEvent};
+use crate::source::{EventSource, Trigger};
+use crate::stagger::{Candidate, OffsetSuggestion};
+use crate::store::{Delivery, JobState, JobStore, StoreUpdate};
+use chrono::{DateTime, Duration, NaiveTime, Utc};
+#[cfg(feature = "serde")]
+use serde::Serialize;
+use std::any::Any;
+use std::cell::Cell;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+use std::thread::JoinHandle;
+use std::time::Instant;
+
+const DEFAULT_HISTORY_LIMIT: usize = 1000;
+const DEFAULT_STORE_BUFFER_LIMIT: usize = 10_000;
+const MAX_SLEEP: std::time::Duration = std::time::Duration::from_secs(1);
+const BLOCKED_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(10);
+/// How often `run` polls event sources.
+const EVENT_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(50);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize), serde(transparent))]
+pub struct JobHandle(pub(crate) u64);
+
+impl fmt::Display for JobHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "job {}", self.0)
+    }
+}
+
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct ShutdownReport {
+    /// Runs that had not finished by the deadline.
+    pub still_running: Vec<(JobHandle, RunId)>,
+}
+
+/// What one [`JobRunner::run_pending`] tick did.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct TickReport {
+    /// Runs started, including work given to `submit_now`.
+    pub started: Vec<(JobHandle, RunId)>,
+    /// Due runs that were skipped, and why; also recorded in the audit log.
+    pub skipped: Vec<(JobHandle, AuditAction)>,
+    /// Due or requested jobs left waiting for a free slot.
+    pub queued: Vec<JobHandle>,
+}
+
+/// The runs started by [`JobRunner::trigger_tag_ordered`], in the order they ran.
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct TagReport {
+    /// Each run's outcome, or `None` if the runner shut down before it finished.
+    pub runs: Vec<(JobHandle, RunId, Option<JobOutcome>)>,
+}
+
+impl TagReport {
+    /// Whether every run succeeded; also true if no job has the tag.
+    pub fn succeeded(&self) -> bool {
+        self.runs
+            .iter()
+            .all(|(_, _, outcome)| outcome.as_ref().is_some_and(JobOutcome::is_success))
+    }
+}
+
+/// A snapshot of a registered job, from [`JobRunner::jobs`].
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct JobInfo {
+    pub job: JobHandle,
+    pub job_id: JobId,
+    pub name: Option<String>,
+    /// The human-readable schedule, as from [`JobRunner::describe`].
+    pub schedule: String,
+    pub next_run: Option<DateTime<Utc>>,
+    pub tags: Vec<String>,
+    pub running: bool,
+    /// See [`JobRunner::disable`].
+    pub disabled: bool,
+    pub runs_started: u64,
+    pub last_success: Option<DateTime<Utc>>,
+    pub slo: Option<SloStatus>,
+    /// Everything besides the schedule that decides when and how the job
+    /// runs, e.g. `"priority 5"` or `"timeout 90 seconds"`.
+    pub policies: Vec<String>,
+}
+
+/// Runs a job on only `percent` of its scheduled occurrences until `until`,
+/// spreading the executed occurrences evenly.
+#[derive(Clone, Debug)]
+pub(crate) struct Experiment {
+    percent: u64,
+    until: DateTime<Utc>,
+    occurrences: u64,
+}
+
+/// Called with a job once it has no runs left and is removed.
+pub(crate) type CompleteCallback = Box<dyn FnOnce(JobHandle) + Send>;
+
+/// Called with the job and its last successful run when a watchdog fires.
+type WatchdogCallback = Arc<dyn Fn(JobHandle, Option<DateTime<Utc>>) + Send + Sync>;
+
+/// Alerts once a job has gone `max_gap` without a successful run.
+pub(crate) struct Watchdog {
+    max_gap: Duration,
+    /// Registration time or the last success, whichever is later.
+    since: DateTime<Utc>,
+    alerted: bool,
+    callback: Option<WatchdogCallback>,
+}
+
+impl Watchdog {
+    pub(crate) fn new(max_gap: std::time::Duration, now: DateTime<Utc>) -> Self {
+        Watchdog {
+            max_gap: Duration::from_std(max_gap).unwrap_or(Duration::MAX),
+            since: now,
+            alerted: false,
+            callback: None,
+        }
+    }
+}
+
+impl Experiment {
+    pub(crate) fn new(percent: u8, until: DateTime<Utc>) -> Self {
+        Experiment {
+            percent: u64::from(percent.min(100)),
+            until,
+            occurrences: 0,
+        }
+    }
+
+    fn sample(&mut self, now: DateTime<Utc>) -> bool {
+        if now >= self.until {
+            return true;
+        }
+
+        let n = self.occurrences;
+        self.occurrences += 1;
+        ((n + 1) * self.percent).div_ceil(100) > (n * self.percent).div_ceil(100)
+    }
+}
+
+/// Times of day, in UTC, within which each scheduled run has to start; see
+/// [`JobBuilder::occurrence_window`]. Wraps past midnight if `not_after` is
+/// earlier than `not_before`.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct OccurrenceWindow {
+    pub(crate) not_before: NaiveTime,
+    pub(crate) not_after: NaiveTime,
+}
+
+impl OccurrenceWindow {
+    fn contains(&self, at: DateTime<Utc>) -> bool {
+        let time = at.time();
+        if self.not_before <= self.not_after {
+            self.not_before <= time && time <= self.not_after
+        } else {
+            self.not_before <= time || time <= self.not_after
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct JobOptions {
+    pub(crate) experiment: Option<Experiment>,
+    pub(crate) gate: Option<String>,
+    pub(crate) singleton: bool,
+    pub(crate) window: Option<OccurrenceWindow>,
+    /// Sources that must have sent a heartbeat within the given age.
+    pub(crate) heartbeats: Vec<(String, Duration)>,
+    pub(crate) upstream: Vec<(JobHandle, RunCondition)>,
+    pub(crate) group: Option<String>,
+    pub(crate) delivery: Delivery,
+    pub(crate) auto_stagger: bool,
+    pub(crate) timeout: Option<std::time::Duration>,
+    pub(crate) priority: u8,
+    pub(crate) name: Option<String>,
+    pub(crate) tags: Vec<String>,
+    pub(crate) resources: Vec<String>,
+    pub(crate) watchdog: Option<Watchdog>,
+    pub(crate) slo: Option<Slo>,
+    /// Resolved by the job's first finished run, see [`JobBuilder::do_awaitable`].
+    pub(crate) completion: Option<Completer>,
+    pub(crate) on_complete: Option<CompleteCallback>,
+    /// Polled every tick for jobs registered with [`JobRunner::on_event`].
+    pub(crate) source: Option<Box<dyn EventSource>>,
+    pub(crate) max_consecutive_panics: Option<u32>,
+}
+
+/// A run requested outside the job's schedule, waiting for the next tick.
+struct PendingRun {
+    job: JobHandle,
+    requested_at: DateTime<Utc>,
+    /// Manual triggers skip flag gates and experiment sampling.
+    manual: bool,
+}
+
+/// Work handed to [`JobRunner::submit_now`], waiting for a free slot.
+struct Submission {
+    task: Task,
+    submitted_at: DateTime<Utc>,
+    completer: Completer,
+}
+
+struct RunningTask {
+    job: JobHandle,
+    job_id: JobId,
+    run_id: RunId,
+    group: Option<String>,
+    resources: Vec<String>,
+    scheduled_at: DateTime<Utc>,
+    started_at: DateTime<Utc>,
+    deadline: Option<Instant>,
+    /// Job state persisted once the run finishes, for at-least-once jobs.
+    deferred: Option<StoreUpdate>,
+    /// Resolved when a submitted task finishes.
+    completion: Option<Completer>,
+    thread: JoinHandle<()>,
+}
+
+struct JobEntry {
+    handle: JobHandle,
+    id: JobId,
+    job: Job,
+    last_trigger: Option<DateTime<Utc>>,
+    options: JobOptions,
+    runs_started: u64,
+    last_success: Option<DateTime<Utc>>,
+    consecutive_panics: u32,
+    /// Set by [`JobRunner::disable`] or after too many consecutive panics.
+    disabled: bool,
+}
+
+impl JobEntry {
+    /// See [`JobInfo::policies`]; `panic_limit` is the runner's default.
+    fn policies(&self, panic_limit: Option<u32>) -> Vec<String> {
+        let options = &self.options;
+        let std_duration = |duration: std::time::Duration| {
+            describe_duration(Duration::from_std(duration).unwrap_or(Duration::MAX))
+        };
+        let mut policies = vec![];
+        if self.disabled {
+            policies.push("disabled".to_string());
+        }
+        // Jobs that only run after others already say so in their schedule.
+        let schedule = self.job.describe();
+        for (upstream, condition) in &options.upstream {
+            let policy = match condition {
+                RunCondition::Success => format!("after {upstream} succeeds"),
+                RunCondition::Completion => format!("after {upstream} completes"),
+            };
+            if !schedule.contains(&policy) {
+                policies.push(policy);
+            }
+        }
+        if options.priority != 0 {
+            policies.push(format!("priority {}", options.priority));
+        }
+        if let Some(group) = &options.group {
+            policies.push(format!("exclusive group `{group}`"));
+        }
+        for name in &options.resources {
+            policies.push(format!("requires `{name}`"));
+        }
+        if let Some(timeout) = options.timeout {
+            policies.push(format!("timeout {}", std_duration(timeout)));
+        }
+        if options.delivery == Delivery::AtLeastOnce {
+            policies.push("at-least-once delivery".to_string());
+        }
+        if let Some(flag) = &options.gate {
+            policies.push(format!("gated by `{flag}`"));
+        }
+        if let Some(experiment) = &options.experiment {
+            policies.push(format!(
+                "{}% of runs until {}",
+                experiment.percent,
+                experiment.until.format("%Y-%m-%d %H:%M")
+            ));
+        }
+        for (source, max_age) in &options.heartbeats {
+            policies.push(format!(
+                "needs a heartbeat from `{source}` within {}",
+                describe_duration(*max_age)
+            ));
+        }
+        if let Some(window) = options.window {
+            policies.push(format!(
+                "starts only between {} and {}",
+                window.not_before.format("%H:%M"),
+                window.not_after.format("%H:%M")
+            ));
+        }
+        if options.singleton {
+            policies.push("singleton".to_string());
+        }
+        if options.auto_stagger {
+            policies.push("auto-staggered".to_string());
+        }
+        if let Some(watchdog) = &options.watchdog {
+            policies.push(format!(
+                "alerts after {} without success",
+                describe_duration(watchdog.max_gap)
+            ));
+        }
+        if let Some(slo) = &options.slo {
+            policies.push(slo.describe());
+        }
+        if let Some(limit) = options.max_consecutive_panics.or(panic_limit) {
+            policies.push(format!("disabled after {limit} panics in a row"));
+        }
+        policies
+    }
+
+    fn persisted_state(&self) -> StoreUpdate {
+        StoreUpdate::Job(JobState {
+            job_id: self.id.clone(),
+            next_run: self.job.next_run,
+            remaining_runs: self.job.remaining_runs,
+        })
+    }
+}
+
+/// A job store plus the updates that could not be written to it yet.
+struct Persistence {
+    store: Box<dyn JobStore>,
+    buffer: VecDeque<StoreUpdate>,
+    buffer_limit: usize,
+    degraded: bool,
+    dropped: u64,
+}
+
+impl Persistence {
+    /// Saves `update`, or buffers it while the store is unavailable.
+    fn persist(&mut self, update: StoreUpdate, events: &mut Vec<Event>) {
+        if self.degraded {
+            self.buffer(update);
+            self.try_recover(events);
+        } else if let Err(error) = self.store.save(&update) {
+            self.buffer(update);
+            self.degrade(error.to_string(), events);
+        }
+    }
+
+    fn load(&mut self, job_id: &JobId, events: &mut Vec<Event>) -> Option<JobState> {
+        match self.store.load(job_id) {
+            Ok(state) => state,
+            Err(error) => {
+                if !self.degraded {
+                    self.degrade(error.to_string(), events);
+                }
+                None
+            }
+        }
+    }
+
+    fn degrade(&mut self, error: String, events: &mut Vec<Event>) {
+        #[cfg(feature = "tracing")]
+        tracing::warn!(%error, "job store unavailable, buffering updates");
+        self.degraded = true;
+        events.push(Event::StoreDegraded { error });
+    }
+
+    fn buffer(&mut self, update: StoreUpdate) {
+        if self.buffer.len() >= self.buffer_limit {
+            self.buffer.pop_front();
+            self.dropped += 1;
+        }
+        self.buffer.push_back(update);
+    }
+
+    /// Flushes the buffer in order; stays degraded if the store still fails.
+    fn try_recover(&mut self, events: &mut Vec<Event>) {
+        let mut flushed = 0;
+        while let Some(update) = self.buffer.front() {
+            if self.store.save(update).is_err() {
+                return;
+            }
+            self.buffer.pop_front();
+            flushed += 1;
+        }
+        self.degraded = false;
+        #[cfg(feature = "tracing")]
+        tracing::info!(flushed, dropped = self.dropped, "job store recovered");
+        events.push(Event::StoreRecovered {
+            flushed,
+            dropped: std::mem::take(&mut self.dropped),
+        });
+    }
+}
+
+struct State {
+    /// In registration order, and so sorted by handle.
+    jobs: Vec<JobEntry>,
+    /// Jobs by next run time, so a tick only visits the jobs that are due.
+    /// Entries are left behind when a job is rescheduled or removed and are
+    /// dropped once they come up; see [`State::take_due`].
+    due: BTreeSet<(DateTime<Utc>, JobHandle)>,
+    /// Jobs with an event source, polled on every tick.
+    sources: BTreeSet<JobHandle>,
+    /// Jobs with a watchdog, checked on every tick.
+    watchdogs: BTreeSet<JobHandle>,
+    /// Jobs found without a next run, the only ones
+    /// [`State::remove_finished`] has to look at.
+    without_runs: BTreeSet<JobHandle>,
+    next_handle: u64,
+    trigger_dedup_window: Duration,
+    audit_log: Vec<AuditEntry>,
+    running: Vec<RunningTask>,
+    accepting: bool,
+    paused: bool,
+    flag_provider: Option<Arc<dyn FlagProvider>>,
+    lock_provider: Option<Arc<dyn LockProvider>>,
+    /// Last heartbeat of each source; see [`SchedulerClient::heartbeat`].
+    heartbeats: HashMap<String, DateTime<Utc>>,
+    /// Wrapped around every task, outermost first.
+    layers: Vec<Arc<dyn Layer>>,
+    auto_prune: bool,
+    /// Default for jobs without their own limit; `None` never disables jobs.
+    max_consecutive_panics: Option<u32>,
+    /// Set by [`JobRunner::set_event_queue`]; listeners are called directly without it.
+    bus: Option<Arc<EventBus>>,
+    clock: Arc<dyn Clock>,
+    history: VecDeque<RunRecord>,
+    history_limit: usize,
+    listeners: Vec<Listener>,
+    /// Dependent jobs started by a finished upstream run and queued manual triggers.
+    pending: Vec<PendingRun>,
+    submitted: VecDeque<Submission>,
+    /// Handle and id shared by all submitted work, allocated on first use.
+    adhoc: Option<(JobHandle, JobId)>,
+    ids: Arc<dyn IdGenerator>,
+    max_concurrent_jobs: Option<usize>,
+    /// Permits of each shared resource; see [`JobRunner::resource`].
+    resources: HashMap<String, usize>,
+    persistence: Option<Persistence>,
+    app_state: Option<Arc<dyn Any + Send + Sync>>,
+    /// Timed-out runs whose threads have not returned yet. Their jobs do not
+    /// start again until the thread exits, so a job that always hangs holds
+    /// at most one stray thread.
+    abandoned: HashMap<RunId, JobHandle>,
+    #[cfg(feature = "metrics")]
+    metrics: Metrics,
+}
+
+impl State {
+    fn position(&self, handle: JobHandle) -> Option<usize> {
+        self.jobs
+            .binary_search_by_key(&handle, |entry| entry.handle)
+            .ok()
+    }
+
+    /// Files the job under its current next run; needed whenever that may
+    /// have moved.
+    fn reschedule(&mut self, index: usize) {
+        let entry = &self.jobs[index];
+        match entry.job.next_run {
+            Some(next_run) => self.due.insert((next_run, entry.handle)),
+            None => self.without_runs.insert(entry.handle),
+        };
+    }
+
+    /// Records that the run of `self.jobs[index]` due at `now` was skipped.
+    fn skip(
+        &mut self,
+        index: usize,
+        now: DateTime<Utc>,
+        action: AuditAction,
+        deferred: Option<StoreUpdate>,
+        events: &mut Vec<Event>,
+    ) {
+        let entry = &mut self.jobs[index];
+        #[cfg(feature = "tracing")]
+        tracing::info!(job = %entry.handle, ?action, "scheduled run skipped");
+        self.audit_log.push(AuditEntry {
+            at: now,
+            job: entry.handle,
+            action,
+            run_id: None,
+        });
+        // Nothing runs, so there is nothing to wait for before persisting.
+        if let (Some(update), Some(persistence)) = (deferred, &mut self.persistence) {
+            persistence.persist(update, events);
+        }
+        if entry.job.next_run.is_none() && entry.options.upstream.is_empty() {
+            // The skipped run was the last one; it will never complete.
+            entry.options.completion = None;
+        }
+    }
+
+    /// Removes the jobs due at `now` from the queue and returns their
+    /// positions, earliest first. They must be rescheduled afterwards.
+    fn take_due(&mut self, now: DateTime<Utc>) -> Vec<usize> {
+        let mut due = vec![];
+        while let Some(&(at, handle)) = self.due.first().filter(|(at, _)| *at <= now) {
+            self.due.pop_first();
+            match self.position(handle) {
+                Some(index) if self.jobs[index].job.next_run == Some(at) => due.push(index),
+                _ => {}
+            }
+        }
+        due
+    }
+
+    /// The earliest next run of any job, dropping stale queue entries on the way.
+    fn next_due(&mut self) -> Option<DateTime<Utc>> {
+        while let Some(&(at, handle)) = self.due.first() {
+            match self.position(handle) {
+                Some(index) if self.jobs[index].job.next_run == Some(at) => return Some(at),
+                _ => {
+                    self.due.pop_first();
+                }
+            }
+        }
+        None
+    }
+
+    /// Forecasts the load of the next `horizon` in buckets of `bucket`, with
+    /// run durations estimated from the history.
+    fn forecast(&self, horizon: Duration, bucket: Duration) -> LoadProfile {
+        let mut durations: HashMap<JobHandle, (Duration, i32)> = HashMap::new();
+        for record in &self.history {
+            let (total, count) = durations.entry(record.job).or_insert((Duration::zero(), 0));
+            *total += record.finished_at - record.started_at;
+            *count += 1;
+        }
+        LoadProfile::forecast(
+            self.clock.now(),
+            horizon,
+            bucket,
+            self.jobs.iter().map(|entry| {
+                let mean = durations
+                    .get(&entry.handle)
+                    .map(|(total, count)| *total / *count);
+                (&entry.job, mean)
+            }),
+        )
+    }
+
+    fn offset_suggestions(&self) -> Vec<OffsetSuggestion> {
+        let candidates: Vec<_> = self
+            .jobs
+            .iter()
+            .filter_map(|entry| {
+                let next_run = entry.job.next_run()?;
+                let period = entry.job.next_after(next_run)? - next_run;
+                Some(Candidate {
+                    job: entry.handle,
+                    next_run,
+                    period,
+                    pinned: !entry.options.auto_stagger,
+                })
+            })
+            .collect();
+        // Only the time up to the last candidate's next run matters.
+        let now = self.clock.now();
+        let Some(last) = candidates.iter().map(|candidate| candidate.next_run).max() else {
+            return vec![];
+        };
+        let horizon = (last - now).clamp(Duration::zero(), crate::stagger::MAX_HORIZON)
+            + crate::stagger::BUCKET;
+        let profile = self.forecast(horizon, crate::stagger::BUCKET);
+        crate::stagger::suggest(&profile, candidates)
+    }
+
+    /// Drops history and audit entries older than `retention` allows and
+    /// removes jobs with no runs left that have been idle long enough. A
+    /// job's idle time counts from its last finished run or trigger. Returns
+    /// the removed jobs for their `on_complete` callbacks.
+    fn compact(&mut self, retention: &Retention) -> (CompactionReport, Vec<JobEntry>) {
+        let now = self.clock.now();
+        let mut report = CompactionReport::default();
+
+        let before = self.history.len();
+        let cutoff = now - retention.history;
+        self.history.retain(|record| record.finished_at >= cutoff);
+        report.history_removed = before - self.history.len();
+
+        let before = self.audit_log.len();
+        let cutoff = now - retention.audit_log;
+        self.audit_log.retain(|entry| entry.at >= cutoff);
+        report.audit_entries_removed = before - self.audit_log.len();
+
+        let removed = self.remove_finished(Some(now - retention.finished_jobs));
+        report.jobs_removed = removed.len();
+
+        #[cfg(feature = "tracing")]
+        tracing::info!(?report, "compacted runner state");
+        (report, removed)
+    }
+
+    /// Removes the jobs that can never run again on their own: their schedule
+    /// is exhausted or past its end, they do not run after other jobs or on
+    /// events and no
+    /// run of theirs is running or queued. With `idle_before`, only jobs whose
+    /// last finished run or trigger came earlier are removed.
+    fn remove_finished(&mut self, idle_before: Option<DateTime<Utc>>) -> Vec<JobEntry> {
+        let mut removed = vec![];
+        for handle in std::mem::take(&mut self.without_runs) {
+            let Some(index) = self.position(handle) else {
+                continue;
+            };
+            let entry = &self.jobs[index];
+            // Jobs that run again, or whenever their upstream does, are not finished.
+            if entry.job.next_run.is_some() || !entry.options.upstream.is_empty() {
+                continue;
+            }
+            let idle = idle_before.is_none_or(|cutoff| {
+                let last_finished = self
+                    .history
+                    .iter()
+                    .rev()
+                    .find(|record| record.job == handle)
+                    .map(|record| record.finished_at);
+                last_finished
+                    .max(entry.last_trigger)
+                    .is_none_or(|at| at < cutoff)
+            });
+            if idle
+                && entry.options.source.is_none()
+                && !self.running.iter().any(|running| running.job == handle)
+                && !self.pending.iter().any(|pending| pending.job == handle)
+            {
+                removed.push(self.jobs.remove(index));
+            } else {
+                self.without_runs.insert(handle);
+            }
+        }
+        for entry in &removed {
+            #[cfg(feature = "tracing")]
+            tracing::debug!(job = %entry.handle, "finished job removed");
+            self.forget(entry);
+        }
+        removed
+    }
+
+    /// Drops what the runner keeps about a job that was removed.
+    fn forget(&mut self, entry: &JobEntry) {
+        self.sources.remove(&entry.handle);
+        self.watchdogs.remove(&entry.handle);
+        self.without_runs.remove(&entry.handle);
+        for layer in &self.layers {
+            layer.job_removed(&entry.id);
+        }
+        #[cfg(feature = "metrics")]
+        self.metrics.jobs.remove(&entry.handle);
+    }
+
+    fn can_start(&mut self, group: Option<&str>, resources: &[String]) -> bool {
+        self.running.retain(|running| !running.thread.is_finished());
+        let held = self.held_resources();
+        let below_limit = self
+            .max_concurrent_jobs
+            .is_none_or(|limit| self.running.len() < limit);
+        let group_free = group.is_none_or(|group| {
+            !self
+                .running
+                .iter()
+                .any(|running| running.group.as_deref() == Some(group))
+        });
+        below_limit && group_free && resources_free(resources, &held, &self.resources)
+    }
+
+    /// How many running tasks hold each resource.
+    fn held_resources(&self) -> HashMap<String, usize> {
+        let mut held = HashMap::new();
+        for name in self.running.iter().flat_map(|running| &running.resources) {
+            *held.entry(name.clone()).or_insert(0) += 1;
+        }
+        held
+    }
+
+    /// Starts `task` on a background thread that records its outcome in
+    /// `shared` and notifies listeners when it starts and finishes.
+    fn spawn(
+        &mut self,
+        shared: &Arc<Mutex<State>>,
+        job: JobHandle,
+        job_id: JobId,
+        task: Task,
+        scheduled_at: DateTime<Utc>,
+        deferred: Option<StoreUpdate>,
+    ) -> RunId {
+        let run_id = RunId::from(self.ids.run_id());
+        let entry = self.jobs.iter_mut().find(|entry| entry.handle == job);
+        let group = entry.as_ref().and_then(|entry| entry.options.group.clone());
+        let resources = entry
+            .as_ref()
+            .map_or(vec![], |entry| entry.options.resources.clone());
+        let timeout = entry.as_ref().and_then(|entry| entry.options.timeout);
+        #[cfg(feature = "tracing")]
+        let name = entry.as_ref().and_then(|entry| entry.options.name.clone());
+        let run_number = entry.map_or(1, |entry| {
+            entry.runs_started += 1;
+            entry.runs_started
+        });
+        let context = JobContext {
+            job,
+            job_id: job_id.clone(),
+            run_id: run_id.clone(),
+            scheduled_at,
+            run_number,
+        };
+        let started_at = self.clock.now();
+        let clock = self.clock.clone();
+        let layers = self.layers.clone();
+        let thread_shared = shared.clone();
+
+        let thread = std::thread::spawn(move || {
+            let shared = thread_shared;
+            let JobContext { job, run_id, .. } = context.clone();
+            emit(
+                &shared,
+                &Event::RunStarted {
+                    job,
+                    run_id: run_id.clone(),
+                    scheduled_at,
+                },
+            );
+
+            #[cfg(feature = "tracing")]
+            let span = tracing::info_span!(
+                "job_run",
+                job = %job,
+                name = name.as_deref(),
+                job_id = %context.job_id,
+                run_id = %run_id,
+                scheduled_at = %scheduled_at,
+                run_number,
+            )
+            .entered();
+
+            let ran = Cell::new(false);
+            let outcome = match panic::catch_unwind(AssertUnwindSafe(|| {
+                run_layered(&layers, &context, &|context| {
+                    ran.set(true);
+                    task(context)
+                })
+            })) {
+                Ok(()) if ran.get() => JobOutcome::Success,
+                Ok(()) => JobOutcome::Skipped,
+                Err(payload) => JobOutcome::Panicked(panic_message(payload.as_ref())),
+            };
+
+            #[cfg(feature = "tracing")]
+            {
+                match &outcome {
+                    JobOutcome::Success => tracing::debug!("job finished"),
+                    JobOutcome::Panicked(message) => {
+                        tracing::error!(error = %message, "job panicked")
+                    }
+                    JobOutcome::Skipped => tracing::debug!("job skipped by a layer"),
+                    JobOutcome::TimedOut => {}
+                }
+                drop(span);
+            }
+
+            let mut events = vec![];
+            {
+                let mut state = shared.lock().unwrap();
+                // The run was already recorded as timed out; drop the late result.
+                if state.abandoned.remove(&run_id).is_some() {
+                    return;
+                }
+                let record = RunRecord {
+                    job,
+                    job_id: context.job_id,
+                    run_id,
+                    scheduled_at,
+                    started_at,
+                    finished_at: clock.now(),
+                    outcome,
+                };
+                state.finish(record, &mut events);
+            }
+            for event in &events {
+                emit(&shared, event);
+            }
+        });
+
+        self.running.retain(|running| !running.thread.is_finished());
+        self.running.push(RunningTask {
+            job,
+            job_id,
+            run_id: run_id.clone(),
+            group,
+            resources,
+            scheduled_at,
+            started_at,
+            deadline: timeout.map(|timeout| Instant::now() + timeout),
+            deferred,
+            completion: None,
+            thread,
+        });
+        run_id
+    }
+
+    /// Records a finished run: persists it, adds it to the history, queues the
+    /// jobs that depend on it and frees its slot.
+    fn finish(&mut self, record: RunRecord, events: &mut Vec<Event>) {
+        let index = self
+            .running
+            .iter()
+            .position(|running| running.run_id == record.run_id);
+        let (deferred, completion) = match index {
+            Some(index) => {
+                let running = self.running.remove(index);
+                (running.deferred, running.completion)
+            }
+            None => (None, None),
+        };
+        if let Some(completer) = completion {
+            completer.complete(Some(record.outcome.clone()));
+        }
+        if let Some(persistence) = &mut self.persistence {
+            if let Some(update) = deferred {
+                persistence.persist(update, events);
+            }
+            persistence.persist(StoreUpdate::Run(record.clone()), events);
+        }
+        let skipped = matches!(record.outcome, JobOutcome::Skipped);
+        // A job cancelled mid-run has already had its metrics dropped.
+        #[cfg(feature = "metrics")]
+        if !skipped && self.position(record.job).is_some() {
+            self.metrics
+                .jobs
+                .entry(record.job)
+                .or_insert_with(|| JobMetrics::new(record.job, record.job_id.clone()))
+                .record(&record);
+        }
+        let max_consecutive_panics = self.max_consecutive_panics;
+        if let Some(entry) = self
+            .jobs
+            .iter_mut()
+            .find(|entry| entry.handle == record.job)
+        {
+            if let Some(completer) = entry.options.completion.take() {
+                completer.complete(Some(record.outcome.clone()));
+            }
+            if matches!(record.outcome, JobOutcome::Panicked(_)) {
+                entry.consecutive_panics += 1;
+                let limit = entry
+                    .options
+                    .max_consecutive_panics
+                    .or(max_consecutive_panics);
+                if !entry.disabled && limit.is_some_and(|limit| entry.consecutive_panics >= limit) {
+                    #[cfg(feature = "tracing")]
+                    tracing::error!(
+                        job = %entry.handle,
+                        panics = entry.consecutive_panics,
+                        "job disabled after consecutive panics"
+                    );
+                    entry.disabled = true;
+                    events.push(Event::JobDisabled {
+                        job: entry.handle,
+                        consecutive_panics: entry.consecutive_panics,
+                    });
+                }
+            } else if !skipped {
+                entry.consecutive_panics = 0;
+            }
+            if let Some(slo) = entry.options.slo.as_mut().filter(|_| !skipped) {
+                let success = record.outcome.is_success();
+                if let Some(success_rate) = slo.record(record.finished_at, success) {
+                    let target = slo.target;
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(job = %entry.handle, success_rate, target, "job breached its SLO");
+                    events.push(Event::SloBreached {
+                        job: entry.handle,
+                        success_rate,
+                        target,
+                    });
+                }
+            }
+            if record.outcome.is_success() {
+                entry.last_success = Some(record.finished_at);
+                if let Some(watchdog) = &mut entry.options.watchdog {
+                    watchdog.since = watchdog.since.max(record.finished_at);
+                    watchdog.alerted = false;
+                }
+            }
+        }
+        self.history.push_back(record.clone());
+        while self.history.len() > self.history_limit {
+            self.history.pop_front();
+        }
+
+        let dependents: Vec<_> = self
+            .jobs
+            .iter()
+            .filter(|entry| {
+                entry.options.upstream.iter().any(|(upstream, condition)| {
+                    *upstream == record.job && condition.matches(&record.outcome)
+                })
+            })
+            .map(|entry| PendingRun {
+                job: entry.handle,
+                requested_at: record.finished_at,
+                manual: false,
+            })
+            .collect();
+        self.pending.extend(dependents);
+        events.push(Event::RunFinished(record));
+    }
+
+    /// The job that tasks from [`submit_now`](SchedulerClient::submit_now)
+    /// run under, so their records and completions have a handle and id.
+    fn adhoc_job(&mut self) -> (JobHandle, JobId) {
+        if self.adhoc.is_none() {
+            let handle = JobHandle(self.next_handle);
+            self.next_handle += 1;
+            self.adhoc = Some((handle, JobId::from(self.ids.job_id())));
+        }
+        self.adhoc.clone().expect("allocated above")
+    }
+
+    /// Starts a submitted task and ties its completion to the run.
+    fn spawn_submission(
+        &mut self,
+        shared: &Arc<Mutex<State>>,
+        submission: Submission,
+    ) -> (JobHandle, RunId) {
+        let (job, job_id) = self.adhoc_job();
+        let run_id = self.spawn(
+            shared,
+            job,
+            job_id,
+            submission.task,
+            submission.submitted_at,
+            None,
+        );
+        // The run cannot finish before this: finishing needs the lock we hold.
+        if let Some(running) = self
+            .running
+            .iter_mut()
+            .find(|running| running.run_id == run_id)
+        {
+            running.completion = Some(submission.completer);
+        }
+        (job, run_id)
+    }
+
+    /// Fires the watchdogs of jobs that have gone too long without a
+    /// successful run, once per gap. Returns the callbacks to call once the
+    /// lock is released.
+    fn check_watchdogs(
+        &mut self,
+        now: DateTime<Utc>,
+        events: &mut Vec<Event>,
+    ) -> Vec<(WatchdogCallback, JobHandle, Option<DateTime<Utc>>)> {
+        let mut alerts = vec![];
+        for &handle in &self.watchdogs {
+            let Some(index) = self.position(handle) else {
+                continue;
+            };
+            let entry = &mut self.jobs[index];
+            let Some(watchdog) = &mut entry.options.watchdog else {
+                continue;
+            };
+            if watchdog.alerted || now - watchdog.since <= watchdog.max_gap {
+                continue;
+            }
+            #[cfg(feature = "tracing")]
+            tracing::warn!(job = %entry.handle, "job missed its completion deadline");
+            watchdog.alerted = true;
+            events.push(Event::CompletionOverdue {
+                job: entry.handle,
+                last_success: entry.last_success,
+            });
+            if let Some(callback) = &watchdog.callback {
+                alerts.push((callback.clone(), entry.handle, entry.last_success));
+            }
+        }
+        alerts
+    }
+
+    /// Records runs that outlived their job's timeout as failed and frees their
+    /// slots. Their threads cannot be stopped and keep running detached; the
+    /// job itself stays busy until they return.
+    fn expire_timeouts(&mut self, events: &mut Vec<Event>) {
+        let now = Instant::now();
+        let expired: Vec<_> = self
+            .running
+            .iter()
+            .filter(|running| {
+                running.deadline.is_some_and(|deadline| deadline <= now)
+                    && !running.thread.is_finished()
+            })
+            .map(|running| RunRecord {
+                job: running.job,
+                job_id: running.job_id.clone(),
+                run_id: running.run_id.clone(),
+                scheduled_at: running.scheduled_at,
+                started_at: running.started_at,
+                finished_at: self.clock.now(),
+                outcome: JobOutcome::TimedOut,
+            })
+            .collect();
+        for record in expired {
+            #[cfg(feature = "tracing")]
+            tracing::error!(job = %record.job, run_id = %record.run_id, "job timed out");
+            self.abandoned.insert(record.run_id.clone(), record.job);
+            self.finish(record, events);
+        }
+    }
+}
+
+/// Hands `event` to the event queue if there is one, or else calls every
+/// listener, without holding the state lock so listeners may use the runner.
+fn emit(shared: &Mutex<State>, event: &Event) {
+    let (bus, listeners) = {
+        let state = shared.lock().unwrap();
+        (state.bus.clone(), state.listeners.clone())
+    };
+    match bus {
+        Some(bus) => bus.publish(event.clone()),
+        None => deliver(shared, &listeners, event),
+    }
+}
+
+#[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+fn deliver(shared: &Mutex<State>, listeners: &[Listener], event: &Event) {
+    #[cfg(feature = "metrics")]
+    let started = Instant::now();
+    for listener in listeners {
+        // A panicking listener must not take down a task thread or the tick.
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| listener(event)));
+    }
+    #[cfg(feature = "metrics")]
+    if !listeners.is_empty() {
+        let elapsed = started.elapsed().as_secs_f64();
+        shared
+            .lock()
+            .unwrap()
+            .metrics
+            .listener_latency
+            .observe(elapsed);
+    }
+}
+
+/// Delivers the events queued on `bus` until it is replaced or the runner is
+/// dropped.
+fn dispatch(shared: Weak<Mutex<State>>, bus: Arc<EventBus>) {
+    while !bus.is_finished() {
+        let Some(event) = bus.next(std::time::Duration::from_millis(200)) else {
+            if shared.strong_count() == 0 {
+                return;
+            }
+            continue;
+        };
+        let Some(shared) = shared.upgrade() else {
+            return;
+        };
+        let listeners = shared.lock().unwrap().listeners.clone();
+        deliver(&shared, &listeners, &event);
+    }
+}
+
+/// Asks `provider` about `flag`, counting a panic as off.
+fn flag_enabled(provider: &dyn FlagProvider, flag: &str) -> bool {
+    panic::catch_unwind(AssertUnwindSafe(|| provider.is_enabled(flag))).unwrap_or(false)
+}
+
+/// Whether following `upstream` links from `from` leads to `to`.
+fn reaches(upstream: &HashMap<JobHandle, Vec<JobHandle>>, from: JobHandle, to: JobHandle) -> bool {
+    let mut seen = HashSet::new();
+    let mut pending: Vec<_> = upstream.get(&from).cloned().unwrap_or_default();
+    while let Some(job) = pending.pop() {
+        if job == to {
+            return true;
+        }
+        if seen.insert(job) {
+            pending.extend(upstream.get(&job).into_iter().flatten());
+        }
+    }
+    false
+}
+
+/// Calls the `on_complete` callbacks of removed jobs; the lock must not be held.
+fn notify_completed(shared: &Mutex<State>, removed: Vec<JobEntry>) {
+    for entry in removed {
+        if let Some(on_complete) = entry.options.on_complete {
+            call_guarded(shared, entry.handle, "on_complete", || {
+                on_complete(entry.handle)
+            });
+        }
+    }
+}
+
+/// Runs a user callback on the ticking thread, turning a panic into an
+/// [`Event::CallbackPanicked`] so it cannot unwind out of the tick.
+fn call_guarded(shared: &Mutex<State>, job: JobHandle, callback: &'static str, f: impl FnOnce()) {
+    if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(f)) {
+        #[cfg(feature = "tracing")]
+        tracing::error!(job = %job, callback, "callback panicked");
+        let message = panic_message(payload.as_ref());
+        emit(
+            shared,
+            &Event::CallbackPanicked {
+                job,
+                callback,
+                message,
+            },
+        );
+    }
+}
+
+/// Whether one more task holding `required` fits within the resources' permits.
+fn resources_free(
+    required: &[String],
+    held: &HashMap<String, usize>,
+    permits: &HashMap<String, usize>,
+) -> bool {
+    required.iter().all(|name| {
+        let held = held.get(name).copied().unwrap_or(0);
+        held < permits.get(name).copied().unwrap_or(1)
+    })
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "task panicked".to_string()
+    }
+}
+
+/// Cloneable handle for registering, cancelling and triggering jobs from any
+/// thread, including from inside running tasks.
+#[derive(Clone)]
+pub struct SchedulerClient {
+    state: Arc<Mutex<State>>,
+}
+
+impl SchedulerClient {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+
+    pub fn every(&self, interval: u64) -> JobBuilder<'_> {
+        JobBuilder::new(self, interval)
+    }
+
+    /// Starts a job that repeats every `interval`, with millisecond resolution.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `interval` is under 1ms or too large to schedule.
+    pub fn every_duration(&self, interval: std::time::Duration) -> JobBuilder<'_> {
+        JobBuilder::with_interval(self, interval)
+    }
+
+    /// Starts a job that runs each time `upstream` finishes successfully,
+    /// instead of on a timer.
+    pub fn after(&self, upstream: JobHandle) -> JobBuilder<'_> {
+        JobBuilder::new(self, 0).after(upstream)
+    }
+
+    /// Links existing jobs so each one runs after the previous one succeeds,
+    /// e.g. backup → upload → cleanup. Every job but the first stops firing on
+    /// its own schedule. Returns `false`, changing nothing, if a handle is
+    /// unknown, appears more than once, or the links would close a loop with
+    /// existing ones, since a job chained after itself would never run again.
+    pub fn chain<I: IntoIterator<Item = JobHandle>>(&self, jobs: I) -> bool {
+        let jobs: Vec<_> = jobs.into_iter().collect();
+        let unique: HashSet<_> = jobs.iter().collect();
+        if unique.len() != jobs.len() {
+            return false;
+        }
+        let mut state = self.lock();
+        if !jobs
+            .iter()
+            .all(|handle| state.jobs.iter().any(|entry| entry.handle == *handle))
+        {
+            return false;
+        }
+        let mut upstream: HashMap<JobHandle, Vec<JobHandle>> = state
+            .jobs
+            .iter()
+            .map(|entry| {
+                let links = entry.options.upstream.iter().map(|(handle, _)| *handle);
+                (entry.handle, links.collect())
+            })
+            .collect();
+        for pair in jobs.windows(2) {
+            upstream.insert(pair[1], vec![pair[0]]);
+        }
+        if jobs.iter().any(|&job| reaches(&upstream, job, job)) {
+            return false;
+        }
+
+        for pair in jobs.windows(2) {
+            let (upstream, downstream) = (pair[0], pair[1]);
+            let entry = state
+                .jobs
+                .iter_mut()
+                .find(|entry| entry.handle == downstream)
+                .unwrap();
+            entry.options.upstream = vec![(upstream, RunCondition::Success)];
+            entry.job.schedule = Box::new(AfterJobs {
+                upstream: entry.options.upstream.clone(),
+            });
+            entry.job.next_run = None;
+        }
+        true
+    }
+
+    /// Starts a job that runs once at `at`, or on the next tick if `at` has
+    /// already passed.
+    pub fn once_at(&self, at: DateTime<Utc>) -> JobBuilder<'_> {
+        JobBuilder::with_scheduler(self, Box::new(OnceAt(at)))
+    }
+
+    /// Starts a job that runs once, `delay` from now.
+    pub fn once_in(&self, delay: std::time::Duration) -> JobBuilder<'_> {
+        let now = self.now();
+        let at = Duration::from_std(delay)
+            .ok()
+            .and_then(|delay| now.checked_add_signed(delay))
+            .unwrap_or(DateTime::<Utc>::MAX_UTC);
+        self.once_at(at)
+    }
+
+    /// Starts a job that runs at sunrise at the given location, in degrees.
+    /// Shift it with [`JobBuilder::offset`].
+    #[cfg(feature = "solar")]
+    pub fn at_sunrise(&self, latitude: f64, longitude: f64) -> JobBuilder<'_> {
+        JobBuilder::with_solar(self, Solar::new(SolarEvent::Sunrise, latitude, longitude))
+    }
+
+    /// Starts a job that runs at sunset at the given location, in degrees.
+    /// Shift it with [`JobBuilder::offset`].
+    #[cfg(feature = "solar")]
+    pub fn at_sunset(&self, latitude: f64, longitude: f64) -> JobBuilder<'_> {
+        JobBuilder::with_solar(self, Solar::new(SolarEvent::Sunset, latitude, longitude))
+    }
+
+    /// Starts a job that runs whenever `source` fires, e.g. when a message
+    /// arrives on an `mpsc::Receiver`. Events between two ticks start a single
+    /// run. The job is removed once the source closes.
+    pub fn on_event<S: EventSource + 'static>(&self, source: S) -> JobBuilder<'_> {
+        JobBuilder::with_source(self, Box::new(source))
+    }
+
+    /// Starts a job from a schedule written as text, e.g. `"every 5 minutes"`,
+    /// `"every monday at 08:30"` or `"every 2 hours starting 2025-01-01"`.
+    pub fn parse(&self, text: &str) -> Result<JobBuilder<'_>, ParseError> {
+        Ok(self.on_schedule(text.parse()?))
+    }
+
+    /// Starts a job from a [`Schedule`], e.g. one loaded from a database.
+    /// Its repeat count becomes the job's.
+    pub fn on_schedule(&self, schedule: Schedule) -> JobBuilder<'_> {
+        JobBuilder::with_schedule(self, schedule)
+    }
+
+    /// Starts a job driven by a custom [`Scheduler`] implementation.
+    pub fn schedule<S: Scheduler + 'static>(&self, scheduler: S) -> JobBuilder<'_> {
+        JobBuilder::with_scheduler(self, Box::new(scheduler))
+    }
+
+    /// Starts a job fired by `trigger`, whether time- or event-driven; the
+    /// same as [`on_schedule`](Self::on_schedule), [`schedule`](Self::schedule)
+    /// or [`on_event`](Self::on_event).
+    pub fn on(&self, trigger: impl Into<Trigger>) -> JobBuilder<'_> {
+        match trigger.into() {
+            Trigger::Schedule(schedule) => self.on_schedule(schedule),
+            Trigger::Custom(scheduler) => JobBuilder::with_scheduler(self, scheduler),
+            Trigger::Event(source) => JobBuilder::with_source(self, source),
+        }
+    }
+
+    /// Registers each block of a [`DynamicJob`] as its own job.
+    pub fn add_dynamic(&self, job: DynamicJob) -> Vec<JobHandle> {
+        let now = self.now();
+        job.schedule_blocks
+            .into_iter()
+            .map(|(window, task)| {
+                let job = Job::new(Box::new(window), task, None, now);
+                self.add_entry(job, JobOptions::default())
+            })
+            .collect()
+    }
+
+    pub(crate) fn now(&self) -> DateTime<Utc> {
+        self.lock().clock.now()
+    }
+
+    /// The state set with [`JobRunner::set_state`], if it has type `S`.
+    pub(crate) fn app_state<S: Send + Sync + 'static>(&self) -> Option<Arc<S>> {
+        let state = self.lock().app_state.clone()?;
+        state.downcast().ok()
+    }
+
+    /// Registers `job`, restoring its state from the job store if one is set.
+    pub(crate) fn add_entry(&self, mut job: Job, options: JobOptions) -> JobHandle {
+        let mut events = vec![];
+        let handle = {
+            let mut state = self.lock();
+            let handle = JobHandle(state.next_handle);
+            state.next_handle += 1;
+            let id = JobId::from(state.ids.job_id());
+            #[cfg(feature = "tracing")]
+            tracing::info!(job = %handle, job_id = %id, schedule = %job.describe(), "job registered");
+            if let Some(persistence) = &mut state.persistence {
+                if let Some(saved) = persistence.load(&id, &mut events) {
+                    job.next_run = saved.next_run;
+                    job.remaining_runs = saved.remaining_runs;
+                }
+            }
+            let has_source = options.source.is_some();
+            let has_watchdog = options.watchdog.is_some();
+            state.jobs.push(JobEntry {
+                handle,
+                id,
+                job,
+                last_trigger: None,
+                options,
+                runs_started: 0,
+                last_success: None,
+                consecutive_panics: 0,
+                disabled: false,
+            });
+            let index = state.jobs.len() - 1;
+            if has_source {
+                state.sources.insert(handle);
+            }
+            if has_watchdog {
+                state.watchdogs.insert(handle);
+            }
+            state.reschedule(index);
+            handle
+        };
+        for event in &events {
+            emit(&self.state, event);
+        }
+        handle
+    }
+
+    /// Removes a job so it no longer fires. A run already in flight is not
+    /// interrupted. Returns `false` if the job was not registered.
+    pub fn cancel(&self, handle: JobHandle) -> bool {
+        let mut state = self.lock();
+        let Some(index) = state.position(handle) else {
+            return false;
+        };
+        let entry = state.jobs.remove(index);
+        state.pending.retain(|pending| pending.job != handle);
+        state.forget(&entry);
+        #[cfg(feature = "tracing")]
+        tracing::info!(job = %handle, "job cancelled");
+        true
+    }
+
+    /// Records that `source`, such as an upstream service, is alive. Jobs
+    /// registered with [`requires_heartbeat`](JobBuilder::requires_heartbeat)
+    /// skip their scheduled runs while its last heartbeat is too old.
+    pub fn heartbeat(&self, source: &str) {
+        let mut state = self.lock();
+        let now = state.clock.now();
+        state.heartbeats.insert(source.to_string(), now);
+    }
+
+    pub fn last_heartbeat(&self, source: &str) -> Option<DateTime<Utc>> {
+        self.lock().heartbeats.get(source).copied()
+    }
+
+    /// Stops the job's scheduled and dependent runs until it is enabled
+    /// again; manual triggers still run it. Returns `false` for unknown jobs.
+    pub fn disable(&self, handle: JobHandle) -> bool {
+        let mut state = self.lock();
+        let Some(entry) = state.jobs.iter_mut().find(|entry| entry.handle == handle) else {
+            return false;
+        };
+        entry.disabled = true;
+        true
+    }
+
+    /// Re-enables a job disabled by hand or after too many consecutive
+    /// panics, resetting its panic count.
+    pub fn enable(&self, handle: JobHandle) -> bool {
+        let mut state = self.lock();
+        let Some(entry) = state.jobs.iter_mut().find(|entry| entry.handle == handle) else {
+            return false;
+        };
+        entry.disabled = false;
+        entry.consecutive_panics = 0;
+        true
+    }
+
+    pub fn is_disabled(&self, handle: JobHandle) -> bool {
+        let state = self.lock();
+        state
+            .jobs
+            .iter()
+            .any(|entry| entry.handle == handle && entry.disabled)
+    }
+
+    pub fn job_id(&self, handle: JobHandle) -> Option<JobId> {
+        let state = self.lock();
+        let entry = state.jobs.iter().find(|entry| entry.handle == handle)?;
+        Some(entry.id.clone())
+    }
+
+    pub fn find_by_id(&self, id: &str) -> Option<JobHandle> {
+        let state = self.lock();
+        let entry = state.jobs.iter().find(|entry| entry.id.as_str() == id)?;
+        Some(entry.handle)
+    }
+
+    /// Runs every job tagged `tag` right away, one after the other, and
+    /// waits for each run to finish before starting the next. Higher
+    /// priorities go first; ties keep registration order. Concurrency limits,
+    /// exclusive groups, resources and pausing are not checked, as for any
+    /// run asked for explicitly, and a failed run does not stop the rest.
+    /// Timeouts only cut a run short while the runner is ticking on another
+    /// thread.
+    pub fn trigger_tag_ordered(&self, tag: &str) -> TagReport {
+        let jobs: Vec<JobHandle> = {
+            let state = self.lock();
+            let mut tagged: Vec<&JobEntry> = state
+                .jobs
+                .iter()
+                .filter(|entry| entry.options.tags.iter().any(|t| t == tag))
+                .collect();
+            tagged.sort_by_key(|entry| std::cmp::Reverse(entry.options.priority));
+            tagged.iter().map(|entry| entry.handle).collect()
+        };
+
+        let mut report = TagReport::default();
+        for handle in jobs {
+            let (run_id, completion) = {
+                let mut state = self.lock();
+                if !state.accepting {
+                    break;
+                }
+                // Cancelled while an earlier job was running.
+                let Some(index) = state.position(handle) else {
+                    continue;
+                };
+                let now = state.clock.now();
+                let entry = &mut state.jobs[index];
+                entry.last_trigger = Some(now);
+                let (job_id, task) = (entry.id.clone(), entry.job.task.clone());
+                #[cfg(feature = "tracing")]
+                tracing::info!(job = %handle, tag, "tagged trigger");
+                let run_id = state.spawn(&self.state, handle, job_id, task, now, None);
+                let completer = Completer::default();
+                let completion = Completion::new(handle, completer.slot());
+                // The run cannot finish before this: finishing needs the lock we hold.
+                if let Some(running) = state
+                    .running
+                    .iter_mut()
+                    .find(|running| running.run_id == run_id)
+                {
+                    running.completion = Some(completer);
+                }
+                state.audit_log.push(AuditEntry {
+                    at: now,
+                    job: handle,
+                    action: AuditAction::Triggered,
+                    run_id: Some(run_id.clone()),
+                });
+                (run_id, completion)
+            };
+            report.runs.push((handle, run_id, completion.wait()));
+        }
+        report
+    }
+
+    pub fn find_by_name(&self, name: &str) -> Option<JobHandle> {
+        let state = self.lock();
+        let entry = state
+            .jobs
+            .iter()
+            .find(|entry| entry.options.name.as_deref() == Some(name))?;
+        Some(entry.handle)
+    }
+
+    pub fn name(&self, handle: JobHandle) -> Option<String> {
+        let state = self.lock();
+        let entry = state.jobs.iter().find(|entry| entry.handle == handle)?;
+        entry.options.name.clone()
+    }
+
+    pub fn next_run(&self, handle: JobHandle) -> Option<DateTime<Utc>> {
+        let state = self.lock();
+        let entry = state.jobs.iter().find(|entry| entry.handle == handle)?;
+        entry.job.next_run()
+    }
+
+    pub fn describe(&self, handle: JobHandle) -> Option<String> {
+        let state = self.lock();
+        let entry = state.jobs.iter().find(|entry| entry.handle == handle)?;
+        Some(entry.job.describe())
+    }
+
+    /// Every registered job, in registration order.
+    pub fn jobs(&self) -> Vec<JobInfo> {
+        let state = self.lock();
+        let now = state.clock.now();
+        state
+            .jobs
+            .iter()
+            .map(|entry| JobInfo {
+                job: entry.handle,
+                job_id: entry.id.clone(),
+                name: entry.options.name.clone(),
+                schedule: entry.job.describe(),
+                next_run: entry.job.next_run,
+                tags: entry.options.tags.clone(),
+                running: state
+                    .running
+                    .iter()
+                    .any(|running| running.job == entry.handle && !running.thread.is_finished()),
+                disabled: entry.disabled,
+                runs_started: entry.runs_started,
+                last_success: entry.last_success,
+                slo: entry.options.slo.as_ref().map(|slo| slo.status(now)),
+                policies: entry.policies(state.max_consecutive_panics),
+            })
+            .collect()
+    }
+
+    /// A dump of the runner's settings and every job's schedule, next run and
+    /// policies, one job per line, to log at startup; [`jobs`](Self::jobs)
+    /// has the same per job as data. All times are UTC.
+    pub fn describe_all(&self) -> String {
+        let jobs = self.jobs();
+        let mut text = match jobs.len() {
+            1 => "1 job, times in UTC".to_string(),
+            count => format!("{count} jobs, times in UTC"),
+        };
+        {
+            let state = self.lock();
+            if let Some(limit) = state.max_concurrent_jobs {
+                text += &format!(", at most {limit} running at once");
+            }
+            if state.paused {
+                text += ", paused";
+            }
+        }
+        for job in jobs {
+            text += &format!("\n{}", job.job);
+            if let Some(name) = &job.name {
+                text += &format!(" `{name}`");
+            }
+            text += &format!(" ({}): {}; ", job.job_id, job.schedule);
+            text += &match job.next_run {
+                Some(at) => format!("next run {}", at.format("%Y-%m-%d %H:%M:%S")),
+                None => "no run scheduled".to_string(),
+            };
+            if !job.policies.is_empty() {
+                text += &format!("; {}", job.policies.join(", "));
+            }
+            if !job.tags.is_empty() {
+                text += &format!("; tagged {}", job.tags.join(", "));
+            }
+        }
+        text
+    }
+
+    /// Every registered job with its human-readable schedule, in registration order.
+    pub fn describe_jobs(&self) -> Vec<(JobHandle, String)> {
+        self.lock()
+            .jobs
+            .iter()
+            .map(|entry| (entry.handle, entry.job.describe()))
+            .collect()
+    }
+
+    /// Runs `task` once, as soon as a concurrency slot is free, through the
+    /// same pipeline as scheduled jobs: it counts against
+    /// [`set_max_concurrent_jobs`](JobRunner::set_max_concurrent_jobs), is
+    /// reported to listeners and recorded in the history. All submitted work
+    /// shares one job handle. Waiting submissions start before due jobs. The
+    /// completion resolves to `None` if the runner shuts down first.
+    pub fn submit_now<F>(&self, task: F) -> Completion
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let task = Mutex::new(Some(task));
+        let task: Task = Arc::new(move |_: &JobContext| {
+            if let Some(task) = task.lock().unwrap().take() {
+                task();
+            }
+        });
+        let completer = Completer::default();
+        let slot = completer.slot();
+
+        let mut state = self.lock();
+        let (job, _) = state.adhoc_job();
+        if state.accepting {
+            let submission = Submission {
+                task,
+                submitted_at: state.clock.now(),
+                completer,
+            };
+            if !state.paused && state.submitted.is_empty() && state.can_start(None, &[]) {
+                state.spawn_submission(&self.state, submission);
+            } else {
+                state.submitted.push_back(submission);
+            }
+        }
+        Completion::new(job, slot)
+    }
+
+    /// Runs a job immediately, regardless of its schedule, leaving its
+    /// cadence untouched. If its exclusive group or a required resource is
+    /// busy, the concurrency limit is reached or the runner is paused, the
+    /// run is queued for the next tick instead. Returns `None` if the handle
+    /// does not belong to this runner or the runner is shut down.
+    pub fn trigger(&self, handle: JobHandle) -> Option<AuditAction> {
+        self.trigger_with(handle, TriggerMode::KeepCadence)
+    }
+
+    /// Like [`trigger`](Self::trigger), for the job with the given name.
+    pub fn trigger_by_name(&self, name: &str) -> Option<AuditAction> {
+        self.trigger(self.find_by_name(name)?)
+    }
+
+    /// Like [`trigger`](Self::trigger), choosing whether the job's next
+    /// scheduled run is computed from now.
+    pub fn trigger_with(&self, handle: JobHandle, mode: TriggerMode) -> Option<AuditAction> {
+        let mut state = self.lock();
+        if !state.accepting {
+            return None;
+        }
+
+        let now = state.clock.now();
+        let window = state.trigger_dedup_window;
+        let index = state.position(handle)?;
+        let entry = &mut state.jobs[index];
+
+        let (mut action, task) = match entry.last_trigger {
+            Some(last) if now - last < window => (AuditAction::Deduplicated, None),
+            _ => {
+                entry.last_trigger = Some(now);
+                if mode == TriggerMode::ResetCadence && entry.job.next_run.is_some() {
+                    entry.job.next_run = entry.job.next_after(now);
+                }
+                (AuditAction::Triggered, Some(entry.job.task.clone()))
+            }
+        };
+        let job_id = entry.id.clone();
+        let group = entry.options.group.clone();
+        let resources = entry.options.resources.clone();
+        state.reschedule(index);
+
+        let mut run_id = None;
+        if let Some(task) = task {
+            let hung = state.abandoned.values().any(|job| *job == handle);
+            if !state.paused && !hung && state.can_start(group.as_deref(), &resources) {
+                run_id = Some(state.spawn(&self.state, handle, job_id, task, now, None));
+            } else {
+                state.pending.push(PendingRun {
+                    job: handle,
+                    requested_at: now,
+                    manual: true,
+                });
+                action = AuditAction::Queued;
+            }
+        }
+        #[cfg(feature = "tracing")]
+        tracing::info!(job = %handle, ?action, "manual trigger");
+        state.audit_log.push(AuditEntry {
+            at: now,
+            job: handle,
+            action: action.clone(),
+            run_id,
+        });
+        Some(action)
+    }
+}
+
+pub struct JobRunner {
+    client: SchedulerClient,
+}
+
+impl Default for JobRunner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl JobRunner {
+    pub fn new() -> Self {
+        Self::with_clock(SystemClock)
+    }
+
+    /// Creates a runner that reads the current time from `clock` instead of the system clock.
+    pub fn with_clock<C: Clock>(clock: C) -> Self {
+        JobRunner {
+            client: SchedulerClient {
+                state: Arc::new(Mutex::new(State {
+                    jobs: vec![],
+                    due: BTreeSet::new(),
+                    sources: BTreeSet::new(),
+                    watchdogs: BTreeSet::new(),
+                    without_runs: BTreeSet::new(),
+                    next_handle: 0,
+                    trigger_dedup_window: Duration::zero(),
+                    audit_log: vec![],
+                    running: vec![],
+                    accepting: true,
+                    paused: false,
+                    flag_provider: None,
+                    lock_provider: None,
+                    heartbeats: HashMap::new(),
+                    layers: vec![],
+                    auto_prune: true,
+                    max_consecutive_panics: None,
+                    bus: None,
+                    clock: Arc::new(clock),
+                    history: VecDeque::new(),
+                    history_limit: DEFAULT_HISTORY_LIMIT,
+                    listeners: vec![],
+                    pending: vec![],
+                    submitted: VecDeque::new(),
+                    adhoc: None,
+                    ids: Arc::new(UuidGenerator),
+                    max_concurrent_jobs: None,
+                    resources: HashMap::new(),
+                    persistence: None,
+                    app_state: None,
+                    abandoned: HashMap::new(),
+                    #[cfg(feature = "metrics")]
+                    metrics: Metrics::new(),
+                })),
+            },
+        }
+    }
+
+    /// Creates a runner whose jobs can receive `state` through
+    /// [`JobBuilder::do_with_state`].
+    pub fn with_state<S: Send + Sync + 'static>(state: S) -> Self {
+        let runner = Self::new();
+        runner.set_state(state);
+        runner
+    }
+
+    pub fn client(&self) -> SchedulerClient {
+        self.client.clone()
+    }
+
+    pub fn every(&self, interval: u64) -> JobBuilder<'_> {
+        self.client.every(interval)
+    }
+
+    pub fn every_duration(&self, interval: std::time::Duration) -> JobBuilder<'_> {
+        self.client.every_duration(interval)
+    }
+
+    pub fn once_at(&self, at: DateTime<Utc>) -> JobBuilder<'_> {
+        self.client.once_at(at)
+    }
+
+    pub fn once_in(&self, delay: std::time::Duration) -> JobBuilder<'_> {
+        self.client.once_in(delay)
+    }
+
+    #[cfg(feature = "solar")]
+    pub fn at_sunrise(&self, latitude: f64, longitude: f64) -> JobBuilder<'_> {
+        self.client.at_sunrise(latitude, longitude)
+    }
+
+    #[cfg(feature = "solar")]
+    pub fn at_sunset(&self, latitude: f64, longitude: f64) -> JobBuilder<'_> {
+        self.client.at_sunset(latitude, longitude)
+    }
+
+    pub fn on_event<S: EventSource + 'static>(&self, source: S) -> JobBuilder<'_> {
+        self.client.on_event(source)
+    }
+
+    pub fn parse(&self, text: &str) -> Result<JobBuilder<'_>, ParseError> {
+        self.client.parse(text)
+    }
+
+    pub fn on_schedule(&self, schedule: Schedule) -> JobBuilder<'_> {
+        self.client.on_schedule(schedule)
+    }
+
+    pub fn schedule<S: Scheduler + 'static>(&self, scheduler: S) -> JobBuilder<'_> {
+        self.client.schedule(scheduler)
+    }
+
+    pub fn on(&self, trigger: impl Into<Trigger>) -> JobBuilder<'_> {
+        self.client.on(trigger)
+    }
+
+    pub fn after(&self, upstream: JobHandle) -> JobBuilder<'_> {
+        self.client.after(upstream)
+    }
+
+    pub fn chain<I: IntoIterator<Item = JobHandle>>(&self, jobs: I) -> bool {
+        self.client.chain(jobs)
+    }
+
+    pub fn add_dynamic(&self, job: DynamicJob) -> Vec<JobHandle> {
+        self.client.add_dynamic(job)
+    }
+
+    pub fn cancel(&self, handle: JobHandle) -> bool {
+        self.client.cancel(handle)
+    }
+
+    pub fn submit_now<F>(&self, task: F) -> Completion
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.client.submit_now(task)
+    }
+
+    pub fn trigger(&self, handle: JobHandle) -> Option<AuditAction> {
+        self.client.trigger(handle)
+    }
+
+    pub fn trigger_by_name(&self, name: &str) -> Option<AuditAction> {
+        self.client.trigger_by_name(name)
+    }
+
+    pub fn trigger_with(&self, handle: JobHandle, mode: TriggerMode) -> Option<AuditAction> {
+        self.client.trigger_with(handle, mode)
+    }
+
+    pub fn heartbeat(&self, source: &str) {
+        self.client.heartbeat(source)
+    }
+
+    pub fn last_heartbeat(&self, source: &str) -> Option<DateTime<Utc>> {
+        self.client.last_heartbeat(source)
+    }
+
+    pub fn disable(&self, handle: JobHandle) -> bool {
+        self.client.disable(handle)
+    }
+
+    pub fn enable(&self, handle: JobHandle) -> bool {
+        self.client.enable(handle)
+    }
+
+    pub fn is_disabled(&self, handle: JobHandle) -> bool {
+        self.client.is_disabled(handle)
+    }
+
+    pub fn job_id(&self, handle: JobHandle) -> Option<JobId> {
+        self.client.job_id(handle)
+    }
+
+    pub fn find_by_id(&self, id: &str) -> Option<JobHandle> {
+        self.client.find_by_id(id)
+    }
+
+    pub fn trigger_tag_ordered(&self, tag: &str) -> TagReport {
+        self.client.trigger_tag_ordered(tag)
+    }
+
+    pub fn find_by_name(&self, name: &str) -> Option<JobHandle> {
+        self.client.find_by_name(name)
+    }
+
+    pub fn name(&self, handle: JobHandle) -> Option<String> {
+        self.client.name(handle)
+    }
+
+    pub fn next_run(&self, handle: JobHandle) -> Option<DateTime<Utc>> {
+        self.client.next_run(handle)
+    }
+
+    pub fn describe(&self, handle: JobHandle) -> Option<String> {
+        self.client.describe(handle)
+    }
+
+    pub fn describe_jobs(&self) -> Vec<(JobHandle, String)> {
+        self.client.describe_jobs()
+    }
+
+    pub fn jobs(&self) -> Vec<JobInfo> {
+        self.client.jobs()
+    }
+
+    pub fn describe_all(&self) -> String {
+        self.client.describe_all()
+    }
+
+    /// Manual triggers for the same job arriving within `window` of the last
+    /// executed trigger are coalesced into that run and audited as deduplicated.
+    pub fn set_trigger_dedup_window(&self, window: Duration) {
+        self.client.lock().trigger_dedup_window = window;
+    }
+
+    /// Sets the application state handed to tasks registered with
+    /// [`JobBuilder::do_with_state`]. Jobs registered earlier keep the state
+    /// they were given.
+    pub fn set_state<S: Send + Sync + 'static>(&self, state: S) {
+        self.client.lock().app_state = Some(Arc::new(state));
+    }
+
+    /// Replaces the generator of job and run identifiers. Jobs registered
+    /// earlier keep their identifiers.
+    pub fn set_id_generator<G: IdGenerator + 'static>(&self, ids: G) {
+        self.client.lock().ids = Arc::new(ids);
+    }
+
+    /// Persists job state and finished runs to `store`. Set it before
+    /// registering jobs so their state can be restored.
+    ///
+    /// If the store starts failing, scheduling carries on from memory: updates
+    /// are buffered (see [`set_store_buffer_limit`](Self::set_store_buffer_limit)),
+    /// [`Event::StoreDegraded`] is emitted, and every tick retries the store
+    /// until the buffer is flushed and [`Event::StoreRecovered`] is emitted.
+    pub fn set_store<S: JobStore + 'static>(&self, store: S) {
+        self.client.lock().persistence = Some(Persistence {
+            store: Box::new(store),
+            buffer: VecDeque::new(),
+            buffer_limit: DEFAULT_STORE_BUFFER_LIMIT,
+            degraded: false,
+            dropped: 0,
+        });
+    }
+
+    /// Caps the updates kept while the store is unavailable; the oldest are
+    /// dropped beyond it.
+    pub fn set_store_buffer_limit(&self, limit: usize) {
+        if let Some(persistence) = &mut self.client.lock().persistence {
+            persistence.buffer_limit = limit.max(1);
+        }
+    }
+
+    pub fn is_store_degraded(&self) -> bool {
+        self.client
+            .lock()
+            .persistence
+            .as_ref()
+            .is_some_and(|persistence| persistence.degraded)
+    }
+
+    /// Run counts, failures, run durations and schedule lag for every
+    /// registered job.
+    #[cfg(feature = "metrics")]
+    pub fn metrics_snapshot(&self) -> MetricsSnapshot {
+        let state = self.client.lock();
+        let jobs = state
+            .jobs
+            .iter()
+            .map(|entry| {
+                let mut metrics = state
+                    .metrics
+                    .jobs
+                    .get(&entry.handle)
+                    .cloned()
+                    .unwrap_or_else(|| JobMetrics::new(entry.handle, entry.id.clone()));
+                metrics.next_run = entry.job.next_run();
+                metrics
+            })
+            .collect();
+        let now = state.clock.now();
+        let mut queue_depths = std::collections::BTreeMap::new();
+        for entry in &state.jobs {
+            let requested = state.pending.iter().any(|run| run.job == entry.handle);
+            if entry.job.is_due(now) || requested {
+                let group = entry.options.group.clone().unwrap_or_default();
+                *queue_depths.entry(group).or_insert(0) += 1;
+            }
+        }
+        let running_tasks = state
+            .running
+            .iter()
+            .filter(|running| !running.thread.is_finished())
+            .count();
+        let runtime = RuntimeMetrics {
+            tick_duration: state.metrics.tick_duration.clone(),
+            listener_latency: state.metrics.listener_latency.clone(),
+            queued_events: state.bus.as_ref().map_or(0, |bus| bus.len()),
+            dropped_events: state.bus.as_ref().map_or(0, |bus| bus.dropped()),
+            registered_jobs: state.jobs.len(),
+            history_len: state.history.len(),
+            audit_log_len: state.audit_log.len(),
+            store_buffer_len: state
+                .persistence
+                .as_ref()
+                .map_or(0, |persistence| persistence.buffer.len()),
+            pending_runs: state.pending.len(),
+            queue_depths,
+            running_tasks,
+            worker_utilization: state
+                .max_concurrent_jobs
+                .map(|limit| running_tasks as f64 / limit.max(1) as f64),
+        };
+        MetricsSnapshot {
+            at: now,
+            jobs,
+            runtime,
+        }
+    }
+
+    /// Forecasts, hour by hour for the next `horizon`, how many runs are due
+    /// and how many tasks are expected to run at once, using the run history
+    /// to estimate durations.
+    pub fn load_profile(&self, horizon: Duration) -> LoadProfile {
+        let state = self.client.lock();
+        state.forecast(horizon, Duration::hours(1))
+    }
+
+    /// The exact times `handle` is expected to fire during the next
+    /// `horizon`, resolving its schedule, time window, offset, end date and
+    /// remaining run count together. Occurrences an experiment samples out or
+    /// a flag gate currently keeps off are left out. Runs after other jobs,
+    /// manual triggers and pauses are not foreseen. `None` if the job is not
+    /// registered.
+    pub fn forecast(&self, handle: JobHandle, horizon: Duration) -> Option<Vec<DateTime<Utc>>> {
+        let (gate, provider) = {
+            let state = self.client.lock();
+            let entry = state.jobs.iter().find(|entry| entry.handle == handle)?;
+            (entry.options.gate.clone(), state.flag_provider.clone())
+        };
+        if let (Some(flag), Some(provider)) = (gate, provider) {
+            if !flag_enabled(provider.as_ref(), &flag) {
+                return Some(vec![]);
+            }
+        }
+        let state = self.client.lock();
+        let entry = state.jobs.iter().find(|entry| entry.handle == handle)?;
+        let now = state.clock.now();
+        let end = now + horizon;
+        let mut experiment = entry.options.experiment.clone();
+        let runs = entry.job.upcoming(now).take(MAX_SIMULATED_RUNS);
+        Some(
+            runs.take_while(|at| *at < end)
+                .filter(|at| {
+                    experiment
+                        .as_mut()
+                        .is_none_or(|experiment| experiment.sample(*at))
+                })
+                .collect(),
+        )
+    }
+
+    /// Applies `retention` once; see [`Retention`].
+    pub fn compact(&self, retention: &Retention) -> CompactionReport {
+        let (report, removed) = self.client.lock().compact(retention);
+        notify_completed(&self.client.state, removed);
+        report
+    }
+
+    /// Removes the jobs with no runs left, such as one-shot jobs that ran and
+    /// schedules past their end, calling their `on_complete` callbacks.
+    /// Returns how many were removed. Runs on every tick unless turned off
+    /// with [`set_auto_prune`](Self::set_auto_prune).
+    pub fn prune(&self) -> usize {
+        let removed = self.client.lock().remove_finished(None);
+        let count = removed.len();
+        notify_completed(&self.client.state, removed);
+        count
+    }
+
+    /// Whether `run_pending` prunes finished jobs; on by default. With it
+    /// off, finished jobs stay registered, e.g. so they can still be
+    /// triggered by hand, until [`prune`](Self::prune) or
+    /// [`compact`](Self::compact) removes them.
+    pub fn set_auto_prune(&self, enabled: bool) {
+        self.client.lock().auto_prune = enabled;
+    }
+
+    /// Number of registered jobs.
+    pub fn len(&self) -> usize {
+        self.client.lock().jobs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Registers a job named `self-maintenance` that runs [`compact`](Self::compact)
+    /// every `retention.interval`, so a long-running scheduler cleans up
+    /// after itself.
+    pub fn enable_self_maintenance(&self, retention: Retention) -> JobHandle {
+        let state = Arc::downgrade(&self.client.state);
+        self.every_duration(retention.interval)
+            .name("self-maintenance")
+            .do_(move || {
+                if let Some(state) = state.upgrade() {
+                    let (_, removed) = state.lock().unwrap().compact(&retention);
+                    notify_completed(&state, removed);
+                }
+            })
+    }
+
+    /// Proposes offsets that spread out jobs whose next runs fall into the
+    /// same minute of the load forecast. Nothing is changed; see [`apply_offsets`](Self::apply_offsets).
+    pub fn suggest_offsets(&self) -> Vec<OffsetSuggestion> {
+        self.client.lock().offset_suggestions()
+    }
+
+    /// Shifts the jobs built with `auto_stagger()` by their suggested offsets
+    /// and returns the suggestions that were applied.
+    pub fn apply_offsets(&self) -> Vec<OffsetSuggestion> {
+        let mut state = self.client.lock();
+        let mut applied = state.offset_suggestions();
+        applied.retain(|suggestion| {
+            let Some(index) = state
+                .position(suggestion.job)
+                .filter(|&index| state.jobs[index].options.auto_stagger)
+            else {
+                return false;
+            };
+            state.jobs[index].job.shift(suggestion.offset);
+            state.reschedule(index);
+            true
+        });
+        applied
+    }
+
+    /// Limits how many tasks may run at once across all jobs. Due jobs over
+    /// the limit wait for a later tick.
+    pub fn set_max_concurrent_jobs(&self, limit: usize) {
+        self.client.lock().max_concurrent_jobs = Some(limit);
+    }
+
+    /// Calls `callback` when the job named `name` has not completed
+    /// successfully for longer than `max_gap`, counting from now or its last
+    /// success. It fires once per gap, alongside an
+    /// [`Event::CompletionOverdue`], and is armed again by the next success.
+    /// Returns `false` if there is no job with that name.
+    pub fn watchdog<F>(&self, name: &str, max_gap: std::time::Duration, callback: F) -> bool
+    where
+        F: Fn(JobHandle, Option<DateTime<Utc>>) + Send + Sync + 'static,
+    {
+        let mut state = self.client.lock();
+        let now = state.clock.now();
+        let Some(entry) = state
+            .jobs
+            .iter_mut()
+            .find(|entry| entry.options.name.as_deref() == Some(name))
+        else {
+            return false;
+        };
+        let since = entry.options.watchdog.as_ref().map_or(now, |old| old.since);
+        entry.options.watchdog = Some(Watchdog {
+            since,
+            callback: Some(Arc::new(callback)),
+            ..Watchdog::new(max_gap, now)
+        });
+        let handle = entry.handle;
+        state.watchdogs.insert(handle);
+        true
+    }
+
+    /// Declares a resource shared by the jobs that [`requires`](crate::JobBuilder::requires)
+    /// it, at most `permits` of which run at once. A due job waits until every
+    /// resource it requires has a free permit. Resources that are required
+    /// but never declared have a single permit.
+    pub fn resource(&self, name: &str, permits: usize) {
+        self.client
+            .lock()
+            .resources
+            .insert(name.to_string(), permits);
+    }
+
+    /// Flags named by `gated_by` are looked up through `provider`, once per
+    /// flag per `run_pending` tick. The runner is not locked while `provider`
+    /// answers, so it may block or use the runner; a panic counts as off.
+    pub fn set_flag_provider<P: FlagProvider + 'static>(&self, provider: P) {
+        self.client.lock().flag_provider = Some(Arc::new(provider));
+    }
+
+    /// Wraps every task the runner executes, scheduled, triggered or
+    /// submitted, in `layer`. Layers added earlier run outermost.
+    pub fn layer<L: Layer + 'static>(&self, layer: L) {
+        self.client.lock().layers.push(Arc::new(layer));
+    }
+
+    /// Scheduled runs of jobs marked `singleton` only start once `provider`
+    /// grants this instance the claim on them. Without a provider they run
+    /// as usual. The runner is not locked while `provider` answers, so it may
+    /// block or use the runner; a panic counts as a refused claim.
+    pub fn set_lock_provider<P: LockProvider + 'static>(&self, provider: P) {
+        self.client.lock().lock_provider = Some(Arc::new(provider));
+    }
+
+    pub fn audit_log(&self) -> Vec<AuditEntry> {
+        self.client.lock().audit_log.clone()
+    }
+
+    /// Delivers events to the listeners from a dedicated thread through a
+    /// queue of at most `capacity` events, so slow listeners cannot hold up
+    /// the scheduler. `policy` decides what happens when the queue is full;
+    /// dropped events are counted in [`dropped_events`](Self::dropped_events).
+    /// Calling it again replaces the queue once the old one is drained.
+    /// [`shutdown`](Self::shutdown) waits, within its timeout, for the queued
+    /// events to be delivered; later events are dropped.
+    pub fn set_event_queue(&self, capacity: usize, policy: OverflowPolicy) {
+        let bus = Arc::new(EventBus::new(capacity, policy));
+        let shared = Arc::downgrade(&self.client.state);
+        let dispatcher = bus.clone();
+        std::thread::Builder::new()
+            .name("job-scheduler-events".to_string())
+            .spawn(move || dispatch(shared, dispatcher))
+            .expect("failed to spawn the event dispatcher");
+        if let Some(old) = self.client.lock().bus.replace(bus) {
+            old.close();
+        }
+    }
+
+    /// Events the event queue discarded because it was full.
+    pub fn dropped_events(&self) -> u64 {
+        self.client
+            .lock()
+            .bus
+            .as_ref()
+            .map_or(0, |bus| bus.dropped())
+    }
+
+    /// Tasks always run isolated: a panic fails only that run, recorded as
+    /// [`JobOutcome::Panicked`]. With a limit set, a job that panics `limit`
+    /// times in a row is also disabled until [`enable`](Self::enable) is
+    /// called, and [`Event::JobDisabled`] is emitted. Jobs can override it
+    /// with [`JobBuilder::disable_after_panics`].
+    pub fn set_max_consecutive_panics(&self, limit: Option<u32>) {
+        self.client.lock().max_consecutive_panics = limit;
+    }
+
+    /// Calls `hook` with every failure: [`Event::RunFinished`] for runs that
+    /// panicked or timed out, and [`Event::CallbackPanicked`] for watchdog and
+    /// `on_complete` callbacks that panicked.
+    pub fn set_error_hook<F: Fn(&Event) + Send + Sync + 'static>(&self, hook: F) {
+        self.add_listener(move |event| {
+            let failed = match event {
+                Event::RunFinished(record) => matches!(
+                    record.outcome,
+                    JobOutcome::Panicked(_) | JobOutcome::TimedOut
+                ),
+                Event::CallbackPanicked { .. } => true,
+                _ => false,
+            };
+            if failed {
+                hook(event);
+            }
+        });
+    }
+
+    /// Registers a callback for runner events. Listeners are called from the
+    /// threads that run the tasks, or from the event queue's thread if there
+    /// is one.
+    pub fn add_listener<F: Fn(&Event) + Send + Sync + 'static>(&self, listener: F) {
+        self.client.lock().listeners.push(Arc::new(listener));
+    }
+
+    /// Finished runs, oldest first. Only the most recent runs are kept; see
+    /// [`set_history_limit`](Self::set_history_limit).
+    pub fn history(&self) -> Vec<RunRecord> {
+        self.client.lock().history.iter().cloned().collect()
+    }
+
+    pub fn history_for(&self, handle: JobHandle) -> Vec<RunRecord> {
+        self.client
+            .lock()
+            .history
+            .iter()
+            .filter(|record| record.job == handle)
+            .cloned()
+            .collect()
+    }
+
+    pub fn set_history_limit(&self, limit: usize) {
+        let mut state = self.client.lock();
+        state.history_limit = limit;
+        while state.history.len() > limit {
+            state.history.pop_front();
+        }
+    }
+
+    /// Looks up the gate flags of the jobs that may start this tick, without
+    /// holding the lock while the flag provider answers.
+    fn gate_flags(&self) -> HashMap<String, bool> {
+        let (provider, gates) = {
+            let state = self.client.lock();
+            let Some(provider) = state.flag_provider.clone() else {
+                return HashMap::new();
+            };
+            let now = state.clock.now();
+            let due = state.due.iter().take_while(|(at, _)| *at <= now);
+            let jobs = due
+                .map(|(_, handle)| *handle)
+                .chain(state.pending.iter().map(|run| run.job));
+            let gates: HashSet<String> = jobs
+                .filter_map(|handle| state.position(handle))
+                .filter_map(|index| state.jobs[index].options.gate.clone())
+                .collect();
+            (provider, gates)
+        };
+        gates
+            .into_iter()
+            .map(|flag| {
+                let enabled = flag_enabled(provider.as_ref(), &flag);
+                (flag, enabled)
+            })
+            .collect()
+    }
+
+    /// Starts the jobs that are due and the runs queued since the last tick.
+    pub fn run_pending(&self) -> TickReport {
+        let flags = self.gate_flags();
+        let mut guard = self.client.lock();
+        let state = &mut *guard;
+        if !state.accepting {
+            return TickReport::default();
+        }
+        #[cfg(feature = "metrics")]
+        let tick_started = Instant::now();
+
+        let now = state.clock.now();
+        let lock_provider = state.lock_provider.clone();
+        let mut report = TickReport::default();
+        let mut due = vec![];
+        let mut claims = vec![];
+        let mut events = vec![];
+        let finished = if state.auto_prune {
+            state.remove_finished(None)
+        } else {
+            vec![]
+        };
+        let mut pending = std::mem::take(&mut state.pending);
+        let mut ended = vec![];
+        for &handle in &state.sources {
+            let Some(index) = state.position(handle) else {
+                continue;
+            };
+            let entry = &mut state.jobs[index];
+            let Some(source) = &mut entry.options.source else {
+                continue;
+            };
+            match source.poll() {
+                // Events since the last tick start one run between them.
+                Some(fired) if fired > 0 && !pending.iter().any(|run| run.job == entry.handle) => {
+                    pending.push(PendingRun {
+                        job: entry.handle,
+                        requested_at: now,
+                        manual: false,
+                    });
+                }
+                Some(_) => {}
+                None => {
+                    entry.options.source = None;
+                    ended.push(index);
+                }
+            }
+        }
+        for index in ended {
+            state.sources.remove(&state.jobs[index].handle);
+            // With its source gone the job may be finished.
+            state.reschedule(index);
+        }
+
+        if let Some(persistence) = state.persistence.as_mut().filter(|p| p.degraded) {
+            persistence.try_recover(&mut events);
+        }
+        state.expire_timeouts(&mut events);
+        let alerts = state.check_watchdogs(now, &mut events);
+
+        state
+            .running
+            .retain(|running| !running.thread.is_finished());
+        let mut active = state.running.len();
+        let mut busy_groups: HashSet<String> = state
+            .running
+            .iter()
+            .filter_map(|running| running.group.clone())
+            .collect();
+        let mut held = state.held_resources();
+
+        // Housekeeping above keeps going while paused; nothing new starts.
+        let mut submitted = vec![];
+        while !state.paused && state.max_concurrent_jobs.is_none_or(|limit| active < limit) {
+            let Some(submission) = state.submitted.pop_front() else {
+                break;
+            };
+            active += 1;
+            submitted.push(submission);
+        }
+
+        // Only jobs that are due or were asked to run are looked at. Higher
+        // priorities claim free slots first; ties keep registration order.
+        let mut order = vec![];
+        if !state.paused {
+            order = state.take_due(now);
+            order.extend(pending.iter().filter_map(|run| state.position(run.job)));
+        }
+        order.sort_unstable();
+        order.dedup();
+        order.sort_by_key(|&index| std::cmp::Reverse(state.jobs[index].options.priority));
+
+        for &index in &order {
+            let entry = &mut state.jobs[index];
+            let requested = pending.iter().find(|run| run.job == entry.handle);
+            let manual = requested.is_some_and(|run| run.manual);
+            if !entry.job.is_due(now) && requested.is_none() {
+                continue;
+            }
+
+            let at_limit = state
+                .max_concurrent_jobs
+                .is_some_and(|limit| active >= limit);
+            let group_busy = entry
+                .options
+                .group
+                .as_ref()
+                .is_some_and(|group| busy_groups.contains(group));
+            let resource_busy = !resources_free(&entry.options.resources, &held, &state.resources);
+            let hung = state.abandoned.values().any(|job| *job == entry.handle);
+            // A flag that was not looked up before the tick is asked next tick.
+            let unknown_flag = match (manual, &entry.options.gate) {
+                (false, Some(flag)) if state.flag_provider.is_some() => !flags.contains_key(flag),
+                _ => false,
+            };
+            if at_limit || group_busy || resource_busy || hung || unknown_flag {
+                #[cfg(feature = "tracing")]
+                tracing::debug!(
+                    job = %entry.handle,
+                    reason = if at_limit {
+                        "concurrency limit"
+                    } else if group_busy {
+                        "group busy"
+                    } else if resource_busy {
+                        "resource busy"
+                    } else if hung {
+                        "timed-out run still running"
+                    } else {
+                        "flag not looked up yet"
+                    },
+                    "due job waiting for a free slot"
+                );
+                report.queued.push(entry.handle);
+                continue;
+            }
+
+            let requested_at = requested.map(|run| run.requested_at);
+            pending.retain(|run| run.job != entry.handle);
+            let scheduled = entry.job.due_task(now);
+            let mut deferred = scheduled.as_ref().map(|_| entry.persisted_state());
+            if entry.options.delivery == Delivery::AtMostOnce {
+                if let (Some(update), Some(persistence)) = (deferred.take(), &mut state.persistence)
+                {
+                    persistence.persist(update, &mut events);
+                }
+            }
+            let Some((task, scheduled_at)) =
+                scheduled.or(requested_at.map(|at| (entry.job.task.clone(), at)))
+            else {
+                continue;
+            };
+
+            let gated_off = match (manual, &entry.options.gate) {
+                (false, Some(flag)) => flags.get(flag) == Some(&false),
+                _ => false,
+            };
+            let stale_heartbeat =
+                entry
+                    .options
+                    .heartbeats
+                    .iter()
+                    .filter(|_| !manual)
+                    .find(|(source, max_age)| {
+                        state
+                            .heartbeats
+                            .get(source)
+                            .is_none_or(|last| now - *last > *max_age)
+                    });
+            let outside_window = entry
+                .options
+                .window
+                .is_some_and(|window| !manual && !window.contains(now));
+            let skipped = if entry.disabled && !manual {
+                Some(AuditAction::Disabled)
+            } else if outside_window {
+                Some(AuditAction::Missed)
+            } else if gated_off {
+                Some(AuditAction::GatedOff)
+            } else if let Some((source, _)) = stale_heartbeat {
+                Some(AuditAction::StaleHeartbeat(source.clone()))
+            } else {
+                match &mut entry.options.experiment {
+                    Some(experiment) if !manual => {
+                        (!experiment.sample(now)).then_some(AuditAction::SampledOut)
+                    }
+                    _ => None,
+                }
+            };
+            let claim = match (manual, &entry.options.name, &lock_provider) {
+                (false, Some(name), Some(_)) if entry.options.singleton => Some(name.clone()),
+                _ => None,
+            };
+            if let Some(action) = skipped {
+                report.skipped.push((entry.handle, action.clone()));
+                state.skip(index, now, action, deferred, &mut events);
+                continue;
+            }
+
+            active += 1;
+            if let Some(group) = &entry.options.group {
+                busy_groups.insert(group.clone());
+            }
+            for name in &entry.options.resources {
+                *held.entry(name.clone()).or_insert(0) += 1;
+            }
+            let run = (entry.handle, entry.id.clone(), task, scheduled_at, deferred);
+            match claim {
+                // Claimed once the lock is released; the slot is kept meanwhile.
+                Some(name) => claims.push((name, run)),
+                None => due.push(run),
+            }
+        }
+        // Jobs left waiting for a slot keep their place, the others move on.
+        for index in order {
+            state.reschedule(index);
+        }
+        state.pending.append(&mut pending);
+
+        for submission in submitted {
+            let started = state.spawn_submission(&self.client.state, submission);
+            report.started.push(started);
+        }
+        for (handle, job_id, task, scheduled_at, deferred) in due {
+            let run_id = state.spawn(
+                &self.client.state,
+                handle,
+                job_id,
+                task,
+                scheduled_at,
+                deferred,
+            );
+            report.started.push((handle, run_id));
+        }
+        #[cfg(feature = "metrics")]
+        {
+            let elapsed = tick_started.elapsed().as_secs_f64();
+            state.metrics.tick_duration.observe(elapsed);
+        }
+        drop(guard);
+
+        if let (Some(provider), false) = (lock_provider, claims.is_empty()) {
+            let claims: Vec<_> = claims
+                .into_iter()
+                .map(|(name, run)| {
+                    let claimed = panic::catch_unwind(AssertUnwindSafe(|| {
+                        provider.try_acquire(&name, run.3)
+                    }));
+                    (claimed.unwrap_or(false), run)
+                })
+                .collect();
+            let mut state = self.client.lock();
+            for (claimed, (handle, job_id, task, scheduled_at, deferred)) in claims {
+                let Some(index) = state.position(handle) else {
+                    continue;
+                };
+                if claimed {
+                    let run_id = state.spawn(
+                        &self.client.state,
+                        handle,
+                        job_id,
+                        task,
+                        scheduled_at,
+                        deferred,
+                    );
+                    report.started.push((handle, run_id));
+                } else {
+                    let action = AuditAction::ClaimedElsewhere;
+                    report.skipped.push((handle, action.clone()));
+                    state.skip(index, now, action, deferred, &mut events);
+                }
+            }
+        }
+
+        for event in &events {
+            emit(&self.client.state, event);
+        }
+        for (callback, job, last_success) in alerts {
+            call_guarded(&self.client.state, job, "watchdog", || {
+                callback(job, last_success)
+            });
+        }
+        notify_completed(&self.client.state, finished);
+        report
+    }
+
+    /// Test utility for runners built with a [`MockClock`]: moves the clock
+    /// forward by `by`, stopping at every fire time on the way so each job runs
+    /// as often as it would have in real time. Waits for the fired tasks to
+    /// finish before returning.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the runner does not use a [`MockClock`].
+    pub fn advance(&self, by: Duration) {
+        let clock = self.client.lock().clock.clone();
+        let mock = (clock.as_ref() as &dyn Any)
+            .downcast_ref::<MockClock>()
+            .expect("JobRunner::advance requires a MockClock");
+
+        let target = mock.now() + by;
+        let mut ticked_at = None;
+        loop {
+            let mut state = self.client.lock();
+            if !state.accepting {
+                return;
+            }
+            let next = if state.paused {
+                None
+            } else if state.pending.is_empty() && state.submitted.is_empty() {
+                state.next_due()
+            } else {
+                Some(mock.now())
+            };
+            drop(state);
+            match next {
+                // Still due after a tick at that time means it waits for a
+                // slot that finished tasks did not free, e.g. a hung run.
+                Some(next) if next <= target && ticked_at.is_none_or(|at| next > at) => {
+                    mock.set(next.max(mock.now()));
+                    ticked_at = Some(mock.now());
+                    self.run_pending();
+                    self.wait_idle();
+                }
+                _ => break,
+            }
+        }
+        mock.set(target);
+        self.run_pending();
+        self.wait_idle();
+    }
+
+    /// Runs jobs until [`shutdown`](Self::shutdown), sleeping until the next
+    /// job is due rather than polling on a fixed tick.
+    pub fn run_forever(&self) {
+        loop {
+            self.run_pending();
+            match self.time_until_next_run() {
+                Some(wait) => std::thread::sleep(wait),
+                None => return,
+            }
+        }
+    }
+
+    /// How long `run_forever` may sleep before the next tick, or `None` once
+    /// the runner has shut down. Capped so triggers and jobs added from other
+    /// threads are still picked up promptly.
+    pub(crate) fn time_until_next_run(&self) -> Option<std::time::Duration> {
+        let mut state = self.client.lock();
+        if !state.accepting {
+            return None;
+        }
+        let now = state.clock.now();
+        let next = state.next_due();
+        let wait = match next {
+            _ if state.paused => MAX_SLEEP,
+            _ if !state.pending.is_empty() || !state.submitted.is_empty() => BLOCKED_POLL_INTERVAL,
+            _ if !state.sources.is_empty() => EVENT_POLL_INTERVAL,
+            // Still due after a tick means it is waiting for a free slot.
+            Some(next) if next <= now => BLOCKED_POLL_INTERVAL,
+            Some(next) => (next - now).to_std().unwrap_or_default(),
+            None => MAX_SLEEP,
+        };
+        let until_timeout = state
+            .running
+            .iter()
+            .filter_map(|running| running.deadline)
+            .min()
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()));
+        Some(wait.min(until_timeout.unwrap_or(MAX_SLEEP)).min(MAX_SLEEP))
+    }
+
+    fn wait_idle(&self) {
+        while !self.running_jobs().is_empty() {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+    }
+
+    /// Jobs that currently have a task executing on a background thread.
+    /// Runs past their timeout are recorded as timed out and not listed.
+    pub fn running_jobs(&self) -> Vec<JobHandle> {
+        let mut events = vec![];
+        let jobs = {
+            let mut state = self.client.lock();
+            state
+                .running
+                .retain(|running| !running.thread.is_finished());
+            state.expire_timeouts(&mut events);
+            state.running.iter().map(|running| running.job).collect()
+        };
+        for event in &events {
+            emit(&self.client.state, event);
+        }
+        jobs
+    }
+
+    /// Stops starting runs until [`resume_all`](Self::resume_all). Running
+    /// tasks finish normally; jobs that fall due while paused run once on
+    /// resume, and manual triggers are queued until then. Timeouts, watchdogs
+    /// and store recovery are still checked on every tick.
+    pub fn pause_all(&self) {
+        self.client.lock().paused = true;
+    }
+
+    pub fn resume_all(&self) {
+        self.client.lock().paused = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.client.lock().paused
+    }
+
+    /// Stops accepting new fire events and waits up to `timeout` for in-flight
+    /// tasks to finish. Tasks still running at the deadline are reported and
+    /// left detached.
+    pub fn shutdown(&self, timeout: std::time::Duration) -> ShutdownReport {
+        self.client.lock().accepting = false;
+
+        let deadline = Instant::now() + timeout;
+        loop {
+            let mut state = self.client.lock();
+            state
+                .running
+                .retain(|running| !running.thread.is_finished());
+            if state.running.is_empty() || Instant::now() >= deadline {
+                for entry in &mut state.jobs {
+                    entry.options.completion = None;
+                }
+                state.submitted.clear();
+                let report = ShutdownReport {
+                    still_running: state
+                        .running
+                        .drain(..)
+                        .map(|running| (running.job, running.run_id))
+                        .collect(),
+                };
+                let bus = state.bus.clone();
+                drop(state);
+                if let Some(bus) = bus {
+                    while !bus.is_idle() && Instant::now() < deadline {
+                        std::thread::sleep(std::time::Duration::from_millis(10));
+                    }
+                    bus.close();
+                }
+                return report;
+            }
+            drop(state);
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    fn runner() -> JobRunner {
+        JobRunner::with_clock(MockClock::new(Utc::now()))
+    }
+
+    /// A store that fails while `failing` is set and logs what it saved.
+    #[derive(Clone, Default)]
+    struct FlakyStore {
+        failing: Arc<AtomicBool>,
+        log: Arc<Mutex<Vec<&'static str>>>,
+    }
+
+    impl JobStore for FlakyStore {
+        fn save(&mut self, update: &StoreUpdate) -> Result<(), crate::StoreError> {
+            if self.failing.load(Ordering::SeqCst) {
+                return Err(crate::StoreError::new("disk full"));
+            }
+            let saved = match update {
+                StoreUpdate::Job(_) => "job",
+                StoreUpdate::Run(_) => "run",
+            };
+            self.log.lock().unwrap().push(saved);
+            Ok(())
+        }
+
+        fn load(&mut self, _: &JobId) -> Result<Option<JobState>, crate::StoreError> {
+            Ok(None)
+        }
+    }
+
+    #[test]
+    fn chain_links_jobs_in_order() {
+        let runner = runner();
+        let a = runner.every(1).minutes().do_(|| {});
+        let b = runner.every(1).minutes().do_(|| {});
+        assert!(runner.chain([a, b]));
+        assert!(runner.next_run(a).is_some());
+        assert_eq!(runner.next_run(b), None);
+    }
+
+    #[test]
+    fn chain_rejects_repeated_handles() {
+        let runner = runner();
+        let a = runner.every(1).minutes().do_(|| {});
+        let b = runner.every(1).minutes().do_(|| {});
+        let before = runner.next_run(a);
+        assert!(!runner.chain([a, a]));
+        assert!(!runner.chain([a, b, a]));
+        assert_eq!(runner.next_run(a), before);
+        assert!(runner.next_run(b).is_some());
+    }
+
+    #[test]
+    fn chain_rejects_loops_with_existing_links() {
+        let runner = runner();
+        let a = runner.every(1).minutes().do_(|| {});
+        let b = runner.every(1).minutes().do_(|| {});
+        let c = runner.every(1).minutes().do_(|| {});
+        assert!(runner.chain([a, b, c]));
+        assert!(!runner.chain([c, a]));
+        assert!(!runner.chain([b, a]));
+        assert!(runner.next_run(a).is_some());
+        assert!(runner.chain([c, runner.every(1).minutes().do_(|| {})]));
+    }
+
+    #[test]
+    fn watchdogs_fire_while_paused() {
+        let runner = runner();
+        let runs = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&runs);
+        runner.every(1).minutes().name("sync").do_(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        let alerted = Arc::new(AtomicUsize::new(0));
+        let alerts = Arc::clone(&alerted);
+        runner.watchdog("sync", std::time::Duration::from_secs(90), move |_, _| {
+            alerts.fetch_add(1, Ordering::SeqCst);
+        });
+        runner.pause_all();
+        runner.advance(Duration::minutes(2));
+        assert_eq!(runs.load(Ordering::SeqCst), 0);
+        assert_eq!(alerted.load(Ordering::SeqCst), 1);
+        runner.resume_all();
+        runner.run_pending();
+        runner.wait_idle();
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn panicking_callbacks_reach_the_error_hook() {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let runner = JobRunner::with_clock(MockClock::new(start));
+        let reported = Arc::new(Mutex::new(vec![]));
+        let errors = Arc::clone(&reported);
+        runner.set_error_hook(move |event| {
+            if let Event::CallbackPanicked { callback, .. } = event {
+                errors.lock().unwrap().push(*callback);
+            }
+        });
+        runner
+            .every(1)
+            .minutes()
+            .name("sync")
+            .do_(|| panic!("sync failed"));
+        runner.watchdog("sync", std::time::Duration::from_secs(30), |_, _| {
+            panic!("pager unreachable")
+        });
+        runner
+            .once_at(start)
+            .on_complete(|_| panic!("cleanup failed"))
+            .do_(|| {});
+        runner.advance(Duration::minutes(1));
+        runner.prune();
+        assert_eq!(*reported.lock().unwrap(), ["watchdog", "on_complete"]);
+    }
+
+    #[test]
+    fn hung_runs_keep_their_job_busy() {
+        let runner = runner();
+        let starts = Arc::new(AtomicUsize::new(0));
+        let released = Arc::new(AtomicBool::new(false));
+        let (counter, gate) = (Arc::clone(&starts), Arc::clone(&released));
+        runner
+            .every(1)
+            .minutes()
+            .timeout(std::time::Duration::from_millis(10))
+            .do_(move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+                while !gate.load(Ordering::SeqCst) {
+                    std::thread::sleep(std::time::Duration::from_millis(1));
+                }
+            });
+        runner.advance(Duration::minutes(1));
+        runner.advance(Duration::minutes(2));
+        assert_eq!(starts.load(Ordering::SeqCst), 1);
+
+        released.store(true, Ordering::SeqCst);
+        while !runner.client.lock().abandoned.is_empty() {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        runner.run_pending();
+        runner.wait_idle();
+        assert_eq!(starts.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn providers_may_use_the_runner() {
+        let runner = runner();
+        let (flags, locks) = (runner.client(), runner.client());
+        runner.set_flag_provider(move |flag: &str| flags.find_by_name(flag).is_some());
+        runner.set_lock_provider(move |job: &str, _: DateTime<Utc>| {
+            locks.find_by_name(job).is_some()
+        });
+        let runs = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&runs);
+        runner
+            .every(1)
+            .minutes()
+            .name("sync")
+            .gated_by("sync")
+            .singleton()
+            .do_(move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+            });
+        runner.every(1).minutes().gated_by("missing").do_(|| {});
+        runner.advance(Duration::minutes(1));
+        let actions: Vec<_> = runner
+            .audit_log()
+            .into_iter()
+            .map(|entry| entry.action)
+            .collect();
+        assert!(runs.load(Ordering::SeqCst) > 0);
+        assert_eq!(actions.len(), runs.load(Ordering::SeqCst));
+        assert!(actions
+            .iter()
+            .all(|action| *action == AuditAction::GatedOff));
+    }
+
+    #[test]
+    fn panicking_providers_skip_the_run() {
+        let runner = runner();
+        runner.set_flag_provider(|_: &str| -> bool { panic!("flag service down") });
+        runner
+            .set_lock_provider(|_: &str, _: DateTime<Utc>| -> bool { panic!("lock service down") });
+        runner.every(1).minutes().gated_by("beta").do_(|| {});
+        runner
+            .every(1)
+            .minutes()
+            .name("report")
+            .singleton()
+            .do_(|| {});
+        runner.advance(Duration::minutes(1));
+        let actions: Vec<_> = runner
+            .audit_log()
+            .into_iter()
+            .map(|entry| entry.action)
+            .collect();
+        assert!(actions.contains(&AuditAction::GatedOff));
+        assert!(actions.contains(&AuditAction::ClaimedElsewhere));
+        assert!(runner.history().is_empty());
+    }
+
+    #[test]
+    fn tick_reports_started_skipped_and_queued_runs() {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let clock = MockClock::new(start);
+        let runner = JobRunner::with_clock(clock.clone());
+        let every_minute = || runner.every(1).minutes().phase(Duration::zero());
+        let (release, gate) = std::sync::mpsc::channel::<()>();
+        let gate = Mutex::new(gate);
+        let first = every_minute().exclusive_group("db").do_(move || {
+            let _ = gate.lock().unwrap().recv();
+        });
+        let second = every_minute().exclusive_group("db").do_(|| {});
+        let disabled = every_minute().do_(|| {});
+        runner.disable(disabled);
+
+        clock.set(start + Duration::minutes(1));
+        let report = runner.run_pending();
+        assert_eq!(
+            report
+                .started
+                .iter()
+                .map(|(job, _)| *job)
+                .collect::<Vec<_>>(),
+            [first]
+        );
+        assert_eq!(report.skipped, [(disabled, AuditAction::Disabled)]);
+        assert_eq!(report.queued, [second]);
+        drop(release);
+        runner.wait_idle();
+    }
+
+    #[test]
+    fn finished_jobs_leave_the_tick_indexes() {
+        let runner = runner();
+        runner.set_auto_prune(false);
+        let (sender, events) = std::sync::mpsc::channel::<()>();
+        runner.on_event(events).do_(|| {});
+        runner.once_in(std::time::Duration::ZERO).do_(|| {});
+        sender.send(()).unwrap();
+        runner.run_pending();
+        runner.wait_idle();
+        assert_eq!(runner.client.lock().sources.len(), 1);
+
+        drop(sender);
+        runner.run_pending();
+        runner.wait_idle();
+        assert_eq!(runner.prune(), 2);
+        let state = runner.client.lock();
+        assert!(state.sources.is_empty());
+        assert!(state.without_runs.is_empty());
+    }
+
+    #[test]
+    fn store_outage_buffers_updates_until_it_recovers() {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let runner = JobRunner::with_clock(MockClock::new(start));
+        let store = FlakyStore::default();
+        runner.set_store(store.clone());
+        runner.set_store_buffer_limit(3);
+        let events = Arc::new(Mutex::new(vec![]));
+        let seen = Arc::clone(&events);
+        runner.add_listener(move |event| match event {
+            Event::StoreDegraded { .. } => seen.lock().unwrap().push((0, 0)),
+            Event::StoreRecovered { flushed, dropped } => {
+                seen.lock().unwrap().push((*flushed, *dropped))
+            }
+            _ => {}
+        });
+        runner.every(1).minutes().phase(Duration::zero()).do_(|| {});
+
+        store.failing.store(true, Ordering::SeqCst);
+        runner.advance(Duration::minutes(2));
+        // Both runs happened; their four updates were buffered, dropping one.
+        assert_eq!(runner.history().len(), 2);
+        assert!(runner.is_store_degraded());
+        assert_eq!(*events.lock().unwrap(), [(0, 0)]);
+        assert!(store.log.lock().unwrap().is_empty());
+
+        store.failing.store(false, Ordering::SeqCst);
+        runner.run_pending();
+        assert!(!runner.is_store_degraded());
+        assert_eq!(*events.lock().unwrap(), [(0, 0), (3, 1)]);
+        assert_eq!(*store.log.lock().unwrap(), ["run", "job", "run"]);
+    }
+
+    #[test]
+    fn delivery_orders_persisting_and_running() {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        for (delivery, before_recovery, after_recovery) in [
+            (Delivery::AtMostOnce, &["job", "task"][..], &["run"][..]),
+            (Delivery::AtLeastOnce, &["task"][..], &["job", "run"][..]),
+        ] {
+            let runner = JobRunner::with_clock(MockClock::new(start));
+            let store = FlakyStore::default();
+            runner.set_store(store.clone());
+            let (failing, log) = (Arc::clone(&store.failing), Arc::clone(&store.log));
+            runner.once_at(start).delivery(delivery).do_(move || {
+                log.lock().unwrap().push("task");
+                // The store goes down while the task runs.
+                failing.store(true, Ordering::SeqCst);
+            });
+            runner.advance(Duration::minutes(1));
+            assert_eq!(*store.log.lock().unwrap(), before_recovery, "{delivery:?}");
+
+            store.failing.store(false, Ordering::SeqCst);
+            runner.run_pending();
+            let log = store.log.lock().unwrap();
+            assert_eq!(
+                log[before_recovery.len()..],
+                *after_recovery,
+                "{delivery:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn full_event_queue_applies_its_overflow_policy() {
+        for (policy, delivered, dropped) in [
+            (OverflowPolicy::DropOldest, &[0, 3][..], 2),
+            (OverflowPolicy::DropNewest, &[0, 1][..], 2),
+            (OverflowPolicy::Block, &[0, 1, 2, 3][..], 0),
+        ] {
+            let runner = runner();
+            runner.set_event_queue(1, policy);
+            let seen = Arc::new(Mutex::new(vec![]));
+            let (entered, listening) = std::sync::mpsc::channel();
+            let (release, released) = std::sync::mpsc::channel::<()>();
+            let (record, released) = (Arc::clone(&seen), Mutex::new(released));
+            runner.add_listener(move |event| {
+                if let Event::CompletionOverdue { job, .. } = event {
+                    record.lock().unwrap().push(job.0);
+                    if job.0 == 0 {
+                        // Hold the dispatcher so the queue fills up.
+                        entered.send(()).unwrap();
+                        let _ = released.lock().unwrap().recv();
+                    }
+                }
+            });
+            let overdue = |job| Event::CompletionOverdue {
+                job: JobHandle(job),
+                last_success: None,
+            };
+            let state = Arc::clone(&runner.client.state);
+            emit(&state, &overdue(0));
+            listening.recv().unwrap();
+            emit(&state, &overdue(1));
+            let publisher = std::thread::spawn(move || {
+                emit(&state, &overdue(2));
+                emit(&state, &overdue(3));
+            });
+            if policy == OverflowPolicy::Block {
+                // The publisher waits for room until the listener lets go.
+                drop(release);
+                publisher.join().unwrap();
+            } else {
+                publisher.join().unwrap();
+                assert_eq!(runner.dropped_events(), dropped, "{policy:?}");
+                drop(release);
+            }
+            while seen.lock().unwrap().len() < delivered.len() {
+                std::thread::sleep(std::time::Duration::from_millis(1));
+            }
+            assert_eq!(*seen.lock().unwrap(), delivered, "{policy:?}");
+            assert_eq!(runner.dropped_events(), dropped, "{policy:?}");
+        }
+    }
+
+    #[test]
+    fn repeated_panics_disable_the_job() {
+        let runner = runner();
+        runner.set_max_consecutive_panics(Some(2));
+        let failures = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&failures);
+        runner.set_error_hook(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        let disabled = Arc::new(Mutex::new(vec![]));
+        let seen = Arc::clone(&disabled);
+        runner.add_listener(move |event| {
+            if let Event::JobDisabled {
+                job,
+                consecutive_panics,
+            } = event
+            {
+                seen.lock().unwrap().push((*job, *consecutive_panics));
+            }
+        });
+        let job = runner.every(1).minutes().do_(|| panic!("always fails"));
+
+        runner.advance(Duration::minutes(5));
+        assert_eq!(runner.history_for(job).len(), 2);
+        assert_eq!(failures.load(Ordering::SeqCst), 2);
+        assert_eq!(*disabled.lock().unwrap(), [(job, 2)]);
+        assert!(runner.is_disabled(job));
+
+        runner.enable(job);
+        runner.advance(Duration::minutes(1));
+        assert_eq!(runner.history_for(job).len(), 3);
+    }
+
+    #[test]
+    fn reset_cadence_leaves_no_stale_run_behind() {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let runner = JobRunner::with_clock(MockClock::new(start));
+        let job = runner.every(10).minutes().do_(|| {});
+        runner.advance(Duration::minutes(5));
+        assert_eq!(runner.next_run(job), Some(start + Duration::minutes(10)));
+
+        runner.trigger_with(job, TriggerMode::ResetCadence);
+        runner.wait_idle();
+        assert_eq!(runner.next_run(job), Some(start + Duration::minutes(15)));
+        // The queue entry for minute 10 is stale and must not fire.
+        runner.advance(Duration::minutes(9));
+        let started = |runner: &JobRunner| {
+            let history = runner.history_for(job);
+            history
+                .iter()
+                .map(|record| record.started_at)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(started(&runner), [start, start + Duration::minutes(5)]);
+        runner.advance(Duration::minutes(1));
+        assert_eq!(
+            started(&runner).last(),
+            Some(&(start + Duration::minutes(15)))
+        );
+        assert_eq!(
+            runner.client.lock().next_due(),
+            Some(start + Duration::minutes(25))
+        );
+    }
+
+    #[test]
+    fn time_and_event_triggers_share_one_entry_point() {
+        let runner = runner();
+        let runs = Arc::new(AtomicUsize::new(0));
+        let count = |runs: &Arc<AtomicUsize>| {
+            let runs = Arc::clone(runs);
+            move || {
+                runs.fetch_add(1, Ordering::SeqCst);
+            }
+        };
+        let (sender, events) = std::sync::mpsc::channel::<()>();
+        let schedule: Schedule = "every 10 minutes".parse().unwrap();
+        runner.on(schedule).do_(count(&runs));
+        runner.on(events).do_(count(&runs));
+        runner
+            .on(Trigger::custom(OnceAt(runner.client.now())))
+            .do_(count(&runs));
+        sender.send(()).unwrap();
+        runner.run_pending();
+        runner.wait_idle();
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+        runner.advance(Duration::minutes(10));
+        assert_eq!(runs.load(Ordering::SeqCst), 4);
+    }
+
+    #[test]
+    fn singleton_runs_on_one_instance() {
+        let claims = Arc::new(Mutex::new(HashSet::new()));
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let runners: Vec<_> = (0..2)
+            .map(|_| {
+                let runner = JobRunner::with_clock(MockClock::new(start));
+                let claims = Arc::clone(&claims);
+                runner.set_lock_provider(move |job: &str, at: DateTime<Utc>| {
+                    claims.lock().unwrap().insert((job.to_string(), at))
+                });
+                runner
+                    .every(10)
+                    .minutes()
+                    .phase(Duration::zero())
+                    .name("report")
+                    .singleton()
+                    .do_(|| {});
+                runner
+            })
+            .collect();
+        for runner in &runners {
+            runner.advance(Duration::minutes(10));
+        }
+        let claimed_elsewhere: usize = runners
+            .iter()
+            .map(|runner| {
+                runner
+                    .audit_log()
+                    .iter()
+                    .filter(|entry| entry.action == AuditAction::ClaimedElsewhere)
+                    .count()
+            })
+            .sum();
+        assert_eq!(claims.lock().unwrap().len(), 1);
+        assert_eq!(claimed_elsewhere, 1);
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn removed_jobs_drop_their_metrics() {
+        let runner = runner();
+        let once = runner.every(1).minutes().repeat(1).do_(|| {});
+        let kept = runner.every(1).minutes().do_(|| {});
+        let cancelled = runner.every(1).minutes().do_(|| {});
+        runner.advance(Duration::minutes(1));
+        runner.prune();
+        assert_eq!(runner.history_for(once).len(), 1);
+        assert_eq!(runner.len(), 2);
+        assert!(!runner.client.lock().metrics.jobs.contains_key(&once));
+
+        assert!(runner.cancel(cancelled));
+        let metrics = &runner.client.lock().metrics;
+        assert!(!metrics.jobs.contains_key(&cancelled));
+        assert!(metrics.jobs.contains_key(&kept));
+    }
+
+    #[test]
+    #[should_panic(expected = "singleton jobs need a name")]
+    fn singleton_requires_a_name() {
+        runner().every(1).minutes().singleton().do_(|| {});
+    }
+
+    #[test]
+    #[should_panic(expected = "every_duration expects at least 1ms")]
+    fn sub_millisecond_intervals_are_rejected() {
+        runner().every_duration(std::time::Duration::from_micros(500));
+    }
+
+    #[test]
+    #[should_panic(expected = "is too large")]
+    fn huge_intervals_are_rejected() {
+        runner().every_duration(std::time::Duration::MAX);
+    }
+}
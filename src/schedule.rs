@@ -0,0 +1,453 @@
+use crate::job::Scheduler;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, Timelike, Utc, Weekday};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// How many times [`Schedule::constrain`] moves a candidate forward before
+/// deciding the constraints can never all hold, e.g. Saturdays except
+/// weekends: two years of days, each tried at most twice.
+const MAX_CONSTRAIN_STEPS: usize = 4 * 366;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum TimeUnit {
+    Milliseconds,
+    Seconds,
+    Minutes,
+    Hours,
+    Days,
+    Weeks,
+}
+
+impl TimeUnit {
+    fn name(&self, plural: bool) -> &'static str {
+        match (self, plural) {
+            (TimeUnit::Milliseconds, false) => "millisecond",
+            (TimeUnit::Milliseconds, true) => "milliseconds",
+            (TimeUnit::Seconds, false) => "second",
+            (TimeUnit::Seconds, true) => "seconds",
+            (TimeUnit::Minutes, false) => "minute",
+            (TimeUnit::Minutes, true) => "minutes",
+            (TimeUnit::Hours, false) => "hour",
+            (TimeUnit::Hours, true) => "hours",
+            (TimeUnit::Days, false) => "day",
+            (TimeUnit::Days, true) => "days",
+            (TimeUnit::Weeks, false) => "week",
+            (TimeUnit::Weeks, true) => "weeks",
+        }
+    }
+}
+
+/// A calendar day within each month that a schedule is restricted to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum MonthDay {
+    /// The `n`th (1 to 5) occurrence of a weekday, e.g. the 2nd Friday.
+    NthWeekday(u8, Weekday),
+    LastDay,
+}
+
+impl MonthDay {
+    fn matches(&self, date: NaiveDate) -> bool {
+        match self {
+            MonthDay::NthWeekday(n, weekday) => {
+                date.weekday() == *weekday && (date.day0() / 7 + 1) == u32::from(*n)
+            }
+            MonthDay::LastDay => date
+                .succ_opt()
+                .is_none_or(|next| next.month() != date.month()),
+        }
+    }
+}
+
+impl fmt::Display for MonthDay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MonthDay::NthWeekday(n, weekday) => {
+                let suffix = match n {
+                    1 => "st",
+                    2 => "nd",
+                    3 => "rd",
+                    _ => "th",
+                };
+                write!(f, "the {n}{suffix} {} of the month", weekday_name(*weekday))
+            }
+            MonthDay::LastDay => write!(f, "the last day of the month"),
+        }
+    }
+}
+
+/// The timing part of a [`Job`](crate::Job): how often it fires, on which
+/// days and times, how many times and between which instants. It can be
+/// built, stored and evaluated without a runner or task; register a job
+/// from one with [`JobRunner::on_schedule`](crate::JobRunner::on_schedule).
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Schedule {
+    pub(crate) interval: u64,
+    pub(crate) time_unit: TimeUnit,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) at_time: Option<NaiveTime>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) weekday: Option<Weekday>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) phase: Option<Duration>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) month_day: Option<MonthDay>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) repeat: Option<i32>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) starts: Option<DateTime<Utc>>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) ends: Option<DateTime<Utc>>,
+    /// Times of day `[start, end)` outside which the job does not fire.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) window: Option<(NaiveTime, NaiveTime)>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) except_weekends: bool,
+}
+
+impl Schedule {
+    /// Fires every `interval` `unit`s.
+    pub fn new(interval: u64, unit: TimeUnit) -> Self {
+        Schedule {
+            interval,
+            time_unit: unit,
+            at_time: None,
+            weekday: None,
+            phase: None,
+            month_day: None,
+            repeat: None,
+            starts: None,
+            ends: None,
+            window: None,
+            except_weekends: false,
+        }
+    }
+
+    pub fn at(mut self, time: NaiveTime) -> Self {
+        self.at_time = Some(time);
+        self
+    }
+
+    pub fn on(mut self, weekday: Weekday) -> Self {
+        self.weekday = Some(weekday);
+        self
+    }
+
+    pub fn on_month_day(mut self, month_day: MonthDay) -> Self {
+        self.month_day = Some(month_day);
+        self
+    }
+
+    /// See [`JobBuilder::phase`](crate::JobBuilder::phase).
+    pub fn phase(mut self, phase: Duration) -> Self {
+        self.phase = Some(phase);
+        self
+    }
+
+    /// Fires at most `count` times.
+    pub fn repeat(mut self, count: i32) -> Self {
+        self.repeat = Some(count);
+        self
+    }
+
+    /// Never fires before `at`.
+    pub fn starting(mut self, at: DateTime<Utc>) -> Self {
+        self.starts = Some(at);
+        self
+    }
+
+    /// Never fires after `at`.
+    pub fn until(mut self, at: DateTime<Utc>) -> Self {
+        self.ends = Some(at);
+        self
+    }
+
+    /// See [`JobBuilder::between`](crate::JobBuilder::between).
+    pub fn between(mut self, start: NaiveTime, end: NaiveTime) -> Self {
+        self.window = Some((start, end));
+        self
+    }
+
+    /// Never fires on Saturdays or Sundays.
+    pub fn except_weekends(mut self) -> Self {
+        self.except_weekends = true;
+        self
+    }
+
+    /// The fire times of a job registered at `now`, in order, honouring the
+    /// repeat count.
+    pub fn occurrences(&self, now: DateTime<Utc>) -> impl Iterator<Item = DateTime<Utc>> + '_ {
+        let limit = self
+            .repeat
+            .map_or(usize::MAX, |count| count.max(0) as usize);
+        std::iter::successors(self.first_run(now), |&last| self.next_after(last)).take(limit)
+    }
+
+    pub(crate) fn interval_duration(&self) -> Duration {
+        match self.time_unit {
+            TimeUnit::Milliseconds => Duration::milliseconds(self.interval as i64),
+            TimeUnit::Seconds => Duration::seconds(self.interval as i64),
+            TimeUnit::Minutes => Duration::minutes(self.interval as i64),
+            TimeUnit::Hours => Duration::hours(self.interval as i64),
+            TimeUnit::Days => Duration::days(self.interval as i64),
+            TimeUnit::Weeks => Duration::weeks(self.interval as i64),
+        }
+    }
+
+    /// Latest slot at or before `now` on the grid `epoch + phase + k * interval`.
+    pub(crate) fn phase_slot(&self, phase: Duration, now: DateTime<Utc>) -> DateTime<Utc> {
+        let period = self.interval_duration().num_milliseconds().max(1);
+        let origin = DateTime::UNIX_EPOCH + phase;
+        let slots = (now - origin).num_milliseconds().div_euclid(period);
+        origin + Duration::milliseconds(slots * period)
+    }
+
+    /// Moves `candidate` forward until it satisfies the at-time, day and
+    /// time-window constraints, or `None` if they never do.
+    fn constrain(&self, mut candidate: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        if let Some(at) = self.at_time {
+            let daily = matches!(self.time_unit, TimeUnit::Days | TimeUnit::Weeks);
+            if candidate.time() < at {
+                candidate = candidate.date_naive().and_time(at).and_utc();
+            } else if candidate.time() > at && daily {
+                // Today's at-time has passed; the day loop below checks tomorrow.
+                candidate = (candidate.date_naive() + Duration::days(1))
+                    .and_time(at)
+                    .and_utc();
+            }
+        }
+        let window_start = self.window.map(|(start, _)| start);
+        let start_of_day = self.at_time.or(window_start).unwrap_or(NaiveTime::MIN);
+        for _ in 0..MAX_CONSTRAIN_STEPS {
+            let time = candidate.time();
+            let next_day = || {
+                (candidate.date_naive() + Duration::days(1))
+                    .and_time(start_of_day)
+                    .and_utc()
+            };
+            if !self.day_matches(candidate.date_naive()) {
+                candidate = next_day();
+            } else if let Some(start) = window_start.filter(|_| !self.in_window(time)) {
+                // An at-time outside the window stays put and never fires.
+                candidate = if time < start && self.at_time.is_none() {
+                    candidate.date_naive().and_time(start).and_utc()
+                } else {
+                    next_day()
+                };
+            } else {
+                return Some(candidate);
+            }
+        }
+        None
+    }
+
+    /// Whether `time` is within the [`between`](Self::between) window, which
+    /// spans midnight if it ends before it starts.
+    fn in_window(&self, time: NaiveTime) -> bool {
+        match self.window {
+            Some((start, end)) if start <= end => start <= time && time < end,
+            Some((start, end)) => start <= time || time < end,
+            None => true,
+        }
+    }
+
+    fn day_matches(&self, date: NaiveDate) -> bool {
+        let weekend = matches!(date.weekday(), Weekday::Sat | Weekday::Sun);
+        !(self.except_weekends && weekend)
+            && self.weekday.is_none_or(|day| date.weekday() == day)
+            && self
+                .month_day
+                .as_ref()
+                .is_none_or(|month_day| month_day.matches(date))
+    }
+
+    /// The equivalent five-field cron expression, if the schedule is anchored
+    /// to the clock with an `at` time in a way cron can express. Plain
+    /// intervals such as "every 5 minutes" have none.
+    pub fn to_cron(&self) -> Option<String> {
+        if self.phase.is_some()
+            || self.month_day.is_some()
+            || self.repeat.is_some()
+            || self.starts.is_some()
+            || self.ends.is_some()
+            || self.window.is_some()
+            || (self.except_weekends && self.weekday.is_some())
+        {
+            return None;
+        }
+        let dow = match self.weekday {
+            Some(day) => day.num_days_from_sunday().to_string(),
+            None if self.except_weekends => "1-5".to_string(),
+            None => "*".to_string(),
+        };
+
+        // Plain intervals count from registration, not from the clock, so
+        // no cron expression fires at the same times.
+        match (&self.time_unit, self.at_time) {
+            (TimeUnit::Days, Some(at)) if self.interval == 1 => {
+                Some(format!("{} {} * * {dow}", at.minute(), at.hour()))
+            }
+            (TimeUnit::Weeks, Some(at)) if self.interval == 1 && self.weekday.is_some() => {
+                Some(format!("{} {} * * {dow}", at.minute(), at.hour()))
+            }
+            _ => None,
+        }
+    }
+
+    fn unbounded_first_run(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let candidate = match self.phase {
+            Some(phase) => self.phase_slot(phase, now) + self.interval_duration(),
+            None => now,
+        };
+        self.constrain(candidate)
+    }
+
+    fn within_end(&self, at: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.ends.is_none_or(|ends| at <= ends).then_some(at)
+    }
+}
+
+impl Scheduler for Schedule {
+    fn first_run(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let now = self.starts.map_or(now, |starts| starts.max(now));
+        self.within_end(self.unbounded_first_run(now)?)
+    }
+
+    fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        if let Some(starts) = self.starts.filter(|&starts| after < starts) {
+            return self.first_run(starts);
+        }
+        let interval = self.interval_duration().max(Duration::milliseconds(1));
+        let candidate = match (self.phase, &self.time_unit, self.at_time) {
+            (Some(phase), _, _) => self.phase_slot(phase, after) + interval,
+            (None, TimeUnit::Days | TimeUnit::Weeks, Some(at)) => {
+                (after.date_naive() + interval).and_time(at).and_utc()
+            }
+            _ => after + interval,
+        };
+        self.within_end(self.constrain(candidate)?)
+    }
+
+    fn describe(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.interval, &self.time_unit) {
+            (1, TimeUnit::Days) if self.month_day.is_some() => write!(f, "monthly")?,
+            (1, TimeUnit::Days) => write!(f, "daily")?,
+            (1, TimeUnit::Weeks) => write!(f, "weekly")?,
+            (1, unit) => write!(f, "every {}", unit.name(false))?,
+            (n, unit) => write!(f, "every {n} {}", unit.name(true))?,
+        }
+        if let Some(at) = self.at_time {
+            write!(f, " at {}", clock_time(at))?;
+        }
+        if let Some(day) = self.weekday {
+            write!(f, " on {}s", weekday_name(day))?;
+        }
+        if let Some(month_day) = &self.month_day {
+            write!(f, " on {month_day}")?;
+        }
+        if let Some((start, end)) = self.window {
+            write!(f, " between {} and {}", clock_time(start), clock_time(end))?;
+        }
+        if self.except_weekends {
+            write!(f, " except weekends")?;
+        }
+        if let Some(phase) = self.phase {
+            write!(f, " offset by {}", describe_duration(phase))?;
+        }
+        if let Some(starts) = self.starts {
+            write!(
+                f,
+                " from {} {}",
+                starts.date_naive(),
+                clock_time(starts.time())
+            )?;
+        }
+        if let Some(ends) = self.ends {
+            write!(
+                f,
+                " until {} {}",
+                ends.date_naive(),
+                clock_time(ends.time())
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Fires once at a fixed instant, or straight away if it has passed.
+pub(crate) struct OnceAt(pub(crate) DateTime<Utc>);
+
+impl Scheduler for OnceAt {
+    fn first_run(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        Some(self.0.max(now))
+    }
+
+    fn next_after(&self, _after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        None
+    }
+
+    fn describe(&self) -> String {
+        format!("once at {}", self.0.format("%Y-%m-%d %H:%M:%S"))
+    }
+}
+
+/// `HH:MM`, with seconds and fractions only when the time has them.
+fn clock_time(time: NaiveTime) -> String {
+    let format = match (time.second(), time.nanosecond()) {
+        (_, nanos) if nanos != 0 => "%H:%M:%S%.f",
+        (0, _) => "%H:%M",
+        _ => "%H:%M:%S",
+    };
+    time.format(format).to_string()
+}
+
+pub(crate) fn weekday_name(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Mon => "Monday",
+        Weekday::Tue => "Tuesday",
+        Weekday::Wed => "Wednesday",
+        Weekday::Thu => "Thursday",
+        Weekday::Fri => "Friday",
+        Weekday::Sat => "Saturday",
+        Weekday::Sun => "Sunday",
+    }
+}
+
+/// Renders a duration in the largest unit that divides it evenly, e.g. "5 minutes".
+pub(crate) fn describe_duration(duration: Duration) -> String {
+    let (count, unit) = largest_unit(duration);
+    format!("{count} {}", unit.name(count != 1))
+}
+
+/// Renders a repetition period, e.g. "every hour" or "every 5 minutes".
+pub(crate) fn describe_every(duration: Duration) -> String {
+    match largest_unit(duration) {
+        (1, unit) => format!("every {}", unit.name(false)),
+        (count, unit) => format!("every {count} {}", unit.name(true)),
+    }
+}
+
+pub(crate) fn largest_unit(duration: Duration) -> (i64, TimeUnit) {
+    let millis = duration.num_milliseconds();
+    [
+        (604_800_000, TimeUnit::Weeks),
+        (86_400_000, TimeUnit::Days),
+        (3_600_000, TimeUnit::Hours),
+        (60_000, TimeUnit::Minutes),
+        (1_000, TimeUnit::Seconds),
+    ]
+    .into_iter()
+    .find(|(size, _)| millis != 0 && millis % size == 0)
+    .map_or((millis, TimeUnit::Milliseconds), |(size, unit)| {
+        (millis / size, unit)
+    })
+}
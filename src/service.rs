@@ -0,0 +1,94 @@
+//! Runs a [`JobRunner`] as a Windows service. Stop shuts the runner down
+//! gracefully, Pause and Continue map to [`JobRunner::pause_all`] and
+//! [`JobRunner::resume_all`].
+//!
+//! The service entry point itself has to live in the binary:
+//!
+//! ```ignore
+//! windows_service::define_windows_service!(ffi_service_main, service_main);
+//!
+//! fn service_main(_arguments: Vec<std::ffi::OsString>) {
+//!     let runner = std::sync::Arc::new(build_runner());
+//!     job_scheduler::service::run("jobs", runner, std::time::Duration::from_secs(30)).unwrap();
+//! }
+//!
+//! fn main() -> windows_service::Result<()> {
+//!     windows_service::service_dispatcher::start("jobs", ffi_service_main)
+//! }
+//! ```
+
+use crate::runner::JobRunner;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::time::Duration;
+use windows_service::service::{
+    ServiceControl, ServiceControlAccept, ServiceExitCode, ServiceState, ServiceStatus, ServiceType,
+};
+use windows_service::service_control_handler::{
+    self, ServiceControlHandlerResult, ServiceStatusHandle,
+};
+
+/// Registers the control handler for `name` and runs `runner` until the
+/// service is stopped, then waits up to `shutdown_timeout` for running tasks.
+/// Call it from the service main function.
+pub fn run(
+    name: &str,
+    runner: Arc<JobRunner>,
+    shutdown_timeout: Duration,
+) -> windows_service::Result<()> {
+    let (controls, received) = mpsc::channel();
+    let status = service_control_handler::register(name, move |control| match control {
+        ServiceControl::Stop | ServiceControl::Pause | ServiceControl::Continue => {
+            let _ = controls.send(control);
+            ServiceControlHandlerResult::NoError
+        }
+        ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+        _ => ServiceControlHandlerResult::NotImplemented,
+    })?;
+
+    report(&status, ServiceState::Running, Duration::ZERO)?;
+    loop {
+        runner.run_pending();
+        let Some(wait) = runner.time_until_next_run() else {
+            break;
+        };
+        match received.recv_timeout(wait) {
+            Ok(ServiceControl::Stop) | Err(RecvTimeoutError::Disconnected) => break,
+            Ok(ServiceControl::Pause) => {
+                runner.pause_all();
+                report(&status, ServiceState::Paused, Duration::ZERO)?;
+            }
+            Ok(ServiceControl::Continue) => {
+                runner.resume_all();
+                report(&status, ServiceState::Running, Duration::ZERO)?;
+            }
+            Ok(_) | Err(RecvTimeoutError::Timeout) => {}
+        }
+    }
+
+    report(&status, ServiceState::StopPending, shutdown_timeout)?;
+    runner.shutdown(shutdown_timeout);
+    report(&status, ServiceState::Stopped, Duration::ZERO)
+}
+
+fn report(
+    status: &ServiceStatusHandle,
+    state: ServiceState,
+    wait_hint: Duration,
+) -> windows_service::Result<()> {
+    let controls_accepted = match state {
+        ServiceState::Running | ServiceState::Paused => {
+            ServiceControlAccept::STOP | ServiceControlAccept::PAUSE_CONTINUE
+        }
+        _ => ServiceControlAccept::empty(),
+    };
+    status.set_service_status(ServiceStatus {
+        service_type: ServiceType::OWN_PROCESS,
+        current_state: state,
+        controls_accepted,
+        exit_code: ServiceExitCode::Win32(0),
+        checkpoint: 0,
+        wait_hint,
+        process_id: None,
+    })
+}
@@ -0,0 +1,148 @@
+use crate::schedule::describe_duration;
+use chrono::{DateTime, Duration, Utc};
+#[cfg(feature = "serde")]
+use serde::Serialize;
+use std::collections::VecDeque;
+
+/// A job's reliability against the target set with
+/// [`JobBuilder::slo`](crate::JobBuilder::slo), as found in
+/// [`JobInfo::slo`](crate::JobInfo::slo).
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct SloStatus {
+    /// The fraction of runs that should succeed, from 0 to 1.
+    pub target: f64,
+    pub window: std::time::Duration,
+    /// Runs finished within the window.
+    pub runs: usize,
+    /// The fraction of those runs that succeeded, or `None` without runs.
+    pub success_rate: Option<f64>,
+}
+
+impl SloStatus {
+    /// Runs the window needs before its success rate is held against the
+    /// target, so that a single early failure does not count as a breach.
+    pub const MIN_RUNS: usize = 5;
+
+    /// Whether the success rate is below the target over at least
+    /// [`MIN_RUNS`](Self::MIN_RUNS) runs.
+    pub fn is_breached(&self) -> bool {
+        self.runs >= Self::MIN_RUNS && self.success_rate.is_some_and(|rate| rate < self.target)
+    }
+}
+
+/// The outcomes of a job's runs over a rolling window.
+pub(crate) struct Slo {
+    pub(crate) target: f64,
+    window: Duration,
+    /// Finish time and success of each run in the window, oldest first.
+    runs: VecDeque<(DateTime<Utc>, bool)>,
+    /// Set once [`Event::SloBreached`](crate::Event::SloBreached) was emitted,
+    /// until the job meets its target again.
+    breached: bool,
+}
+
+impl Slo {
+    /// # Panics
+    ///
+    /// Panics unless `target` is between 0 and 1.
+    pub(crate) fn new(target: f64, window: std::time::Duration) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&target),
+            "success rate must be between 0 and 1, got {target}"
+        );
+        Slo {
+            target,
+            window: Duration::from_std(window).unwrap_or(Duration::MAX),
+            runs: VecDeque::new(),
+            breached: false,
+        }
+    }
+
+    /// Records a run finished at `at`. Returns the success rate if it just
+    /// fell below the target.
+    pub(crate) fn record(&mut self, at: DateTime<Utc>, success: bool) -> Option<f64> {
+        self.runs.push_back((at, success));
+        let cutoff = at.checked_sub_signed(self.window);
+        while self
+            .runs
+            .front()
+            .is_some_and(|(finished, _)| cutoff.is_some_and(|cutoff| *finished <= cutoff))
+        {
+            self.runs.pop_front();
+        }
+        let status = self.status(at);
+        match (status.is_breached(), self.breached) {
+            (true, false) => {
+                self.breached = true;
+                status.success_rate
+            }
+            (breached, _) => {
+                self.breached = breached;
+                None
+            }
+        }
+    }
+
+    pub(crate) fn describe(&self) -> String {
+        let percent = format!("{:.3}", self.target * 100.0);
+        format!(
+            "SLO {}% success over {}",
+            percent.trim_end_matches('0').trim_end_matches('.'),
+            describe_duration(self.window)
+        )
+    }
+
+    pub(crate) fn status(&self, now: DateTime<Utc>) -> SloStatus {
+        let cutoff = now.checked_sub_signed(self.window);
+        let (runs, successes) = self
+            .runs
+            .iter()
+            .filter(|(finished, _)| cutoff.is_none_or(|cutoff| *finished > cutoff))
+            .fold((0, 0), |(runs, successes), (_, success)| {
+                (runs + 1, successes + usize::from(*success))
+            });
+        SloStatus {
+            target: self.target,
+            window: self.window.to_std().unwrap_or(std::time::Duration::MAX),
+            runs,
+            success_rate: (runs > 0).then(|| successes as f64 / runs as f64),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WEEK: std::time::Duration = std::time::Duration::from_secs(7 * 86_400);
+
+    #[test]
+    fn describe_rounds_the_target() {
+        assert_eq!(
+            Slo::new(0.57, WEEK).describe(),
+            "SLO 57% success over 1 week"
+        );
+        assert_eq!(
+            Slo::new(0.9995, WEEK).describe(),
+            "SLO 99.95% success over 1 week"
+        );
+        assert_eq!(
+            Slo::new(1.0, WEEK).describe(),
+            "SLO 100% success over 1 week"
+        );
+    }
+
+    #[test]
+    fn breaches_need_enough_runs() {
+        let mut slo = Slo::new(0.9, WEEK);
+        let start = Utc::now();
+        let at = |run: i64| start + Duration::minutes(run);
+        for run in 0..SloStatus::MIN_RUNS as i64 - 1 {
+            assert_eq!(slo.record(at(run), false), None);
+        }
+        assert!(!slo.status(at(SloStatus::MIN_RUNS as i64)).is_breached());
+        assert_eq!(slo.record(at(SloStatus::MIN_RUNS as i64), false), Some(0.0));
+        assert_eq!(slo.record(at(SloStatus::MIN_RUNS as i64 + 1), false), None);
+    }
+}
@@ -0,0 +1,93 @@
+use crate::job::Scheduler;
+use crate::schedule::describe_duration;
+use chrono::{DateTime, Duration, Utc};
+use sunrise::{Coordinates, SolarDay};
+
+/// How far ahead to look for the next event; near the poles the sun can stay
+/// up or down for months.
+const SEARCH_DAYS: i64 = 366;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SolarEvent {
+    Sunrise,
+    Sunset,
+}
+
+/// Fires at sunrise or sunset at a location, optionally shifted by an offset.
+/// Days on which the event does not happen, such as polar days, are skipped.
+#[derive(Clone, Debug)]
+pub struct Solar {
+    event: SolarEvent,
+    coordinates: Coordinates,
+    offset: Duration,
+}
+
+impl Solar {
+    /// # Panics
+    ///
+    /// Panics if `latitude` is not within ±90 degrees or `longitude` within
+    /// ±180 degrees.
+    pub fn new(event: SolarEvent, latitude: f64, longitude: f64) -> Self {
+        let coordinates = Coordinates::new(latitude, longitude)
+            .unwrap_or_else(|| panic!("invalid coordinates ({latitude}, {longitude})"));
+        Solar {
+            event,
+            coordinates,
+            offset: Duration::zero(),
+        }
+    }
+
+    /// Fires `offset` after the event, or before it if `offset` is negative.
+    pub fn offset(mut self, offset: Duration) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// The first fire time at or after `from`.
+    fn next_from(&self, from: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let event = match self.event {
+            SolarEvent::Sunrise => sunrise::SolarEvent::Sunrise,
+            SolarEvent::Sunset => sunrise::SolarEvent::Sunset,
+        };
+        // Start a day early: the offset and the longitude can move an event
+        // onto the previous or next UTC date.
+        let first = (from - self.offset).date_naive().pred_opt()?;
+        first
+            .iter_days()
+            .take(SEARCH_DAYS as usize + 2)
+            .filter_map(|date| SolarDay::new(self.coordinates, date).event_time(event))
+            .map(|at| at + self.offset)
+            .find(|at| *at >= from)
+    }
+}
+
+impl Scheduler for Solar {
+    fn first_run(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.next_from(now)
+    }
+
+    fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.next_from(after + Duration::milliseconds(1))
+    }
+
+    fn describe(&self) -> String {
+        let event = match self.event {
+            SolarEvent::Sunrise => "sunrise",
+            SolarEvent::Sunset => "sunset",
+        };
+        let place = format!(
+            "({:.4}, {:.4})",
+            self.coordinates.lat(),
+            self.coordinates.lon()
+        );
+        match self.offset {
+            offset if offset > Duration::zero() => {
+                format!("{} after {event} at {place}", describe_duration(offset))
+            }
+            offset if offset < Duration::zero() => {
+                format!("{} before {event} at {place}", describe_duration(-offset))
+            }
+            _ => format!("at {event} at {place}"),
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use crate::job::Scheduler;
+use crate::schedule::Schedule;
+use chrono::{DateTime, Utc};
+use std::sync::mpsc::{Receiver, TryRecvError};
+
+/// What starts a job, for [`JobRunner::on`](crate::JobRunner::on): the
+/// clock, through a [`Schedule`] or a custom [`Scheduler`], or an
+/// [`EventSource`]. Intervals and cron-like calendar times are both
+/// [`Schedule`]s.
+pub enum Trigger {
+    Schedule(Schedule),
+    Custom(Box<dyn Scheduler>),
+    Event(Box<dyn EventSource>),
+}
+
+impl Trigger {
+    pub fn custom<S: Scheduler + 'static>(scheduler: S) -> Self {
+        Trigger::Custom(Box::new(scheduler))
+    }
+
+    pub fn event<S: EventSource + 'static>(source: S) -> Self {
+        Trigger::Event(Box::new(source))
+    }
+}
+
+impl From<Schedule> for Trigger {
+    fn from(schedule: Schedule) -> Self {
+        Trigger::Schedule(schedule)
+    }
+}
+
+impl<T: Send + 'static> From<Receiver<T>> for Trigger {
+    fn from(receiver: Receiver<T>) -> Self {
+        Trigger::event(receiver)
+    }
+}
+
+/// Something other than the clock that fires a job, such as a channel or a
+/// directory watcher; see [`JobRunner::on_event`](crate::JobRunner::on_event).
+/// The runner polls it on every tick.
+pub trait EventSource: Send {
+    /// How many times the source fired since the last poll, or `None` once it
+    /// never will again. Must not block.
+    fn poll(&mut self) -> Option<usize>;
+}
+
+/// Fires once per message; closes when every sender is gone and the channel
+/// is drained.
+impl<T: Send> EventSource for Receiver<T> {
+    fn poll(&mut self) -> Option<usize> {
+        let mut received = 0;
+        loop {
+            match self.try_recv() {
+                Ok(_) => received += 1,
+                Err(TryRecvError::Empty) => return Some(received),
+                Err(TryRecvError::Disconnected) if received > 0 => return Some(received),
+                Err(TryRecvError::Disconnected) => return None,
+            }
+        }
+    }
+}
+
+/// The schedule of jobs started by an [`EventSource`]; it never fires on its own.
+pub(crate) struct OnEvent;
+
+impl Scheduler for OnEvent {
+    fn first_run(&self, _now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        None
+    }
+
+    fn next_after(&self, _after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        None
+    }
+
+    fn describe(&self) -> String {
+        "on events".to_string()
+    }
+}
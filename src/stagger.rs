@@ -0,0 +1,137 @@
+use crate::load::LoadProfile;
+use crate::runner::JobHandle;
+use chrono::{DateTime, Duration, Utc};
+use std::collections::BTreeMap;
+
+/// Aligned jobs are spread over this fraction of their shortest period, so a
+/// staggered job still runs close to its original time.
+const SPREAD_DIVISOR: i32 = 10;
+/// Width of the load-profile buckets jobs are clustered by: jobs starting in
+/// the same minute compete for the same resources.
+pub(crate) const BUCKET: Duration = Duration::minutes(1);
+/// How far ahead clusters are looked for, bounding the cost of the forecast.
+pub(crate) const MAX_HORIZON: Duration = Duration::days(8);
+
+/// A proposed shift for one job of a cluster that starts in the same
+/// load-profile bucket, as returned by
+/// [`JobRunner::suggest_offsets`](crate::JobRunner::suggest_offsets).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OffsetSuggestion {
+    pub job: JobHandle,
+    pub offset: Duration,
+    /// Number of jobs in the cluster, including this one.
+    pub cluster_size: usize,
+}
+
+pub(crate) struct Candidate {
+    pub(crate) job: JobHandle,
+    pub(crate) next_run: DateTime<Utc>,
+    pub(crate) period: Duration,
+    /// Jobs that did not opt into auto-staggering keep their time if possible.
+    pub(crate) pinned: bool,
+}
+
+/// Groups jobs whose next runs fall into the same bucket of `profile` while
+/// it forecasts more than one run starting there, and spreads each group
+/// evenly over a tenth of its shortest period, starting from its earliest
+/// run. The first job of a group keeps its time. Groups too large to spread
+/// at millisecond resolution are left alone.
+pub(crate) fn suggest(profile: &LoadProfile, candidates: Vec<Candidate>) -> Vec<OffsetSuggestion> {
+    let mut clusters: BTreeMap<usize, Vec<Candidate>> = BTreeMap::new();
+    for candidate in candidates {
+        if candidate.period <= Duration::zero() {
+            continue;
+        }
+        let Some(bucket) = profile.bucket_of(candidate.next_run) else {
+            continue;
+        };
+        if profile.buckets[bucket].expected_runs > 1 {
+            clusters.entry(bucket).or_default().push(candidate);
+        }
+    }
+
+    let mut suggestions = vec![];
+    for mut cluster in clusters.into_values() {
+        if cluster.len() < 2 {
+            continue;
+        }
+        cluster.sort_by_key(|candidate| (!candidate.pinned, candidate.next_run, candidate.job));
+        let period = cluster.iter().map(|candidate| candidate.period).min();
+        let step = period.unwrap_or_default() / SPREAD_DIVISOR / cluster.len() as i32;
+        if step < Duration::milliseconds(1) {
+            continue;
+        }
+        let start = cluster[0].next_run;
+        for (index, candidate) in cluster.iter().enumerate().skip(1) {
+            let offset = start + step * index as i32 - candidate.next_run;
+            if offset > Duration::zero() {
+                suggestions.push(OffsetSuggestion {
+                    job: candidate.job,
+                    offset,
+                    cluster_size: cluster.len(),
+                });
+            }
+        }
+    }
+    suggestions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load::LoadBucket;
+    use chrono::TimeZone;
+
+    fn profile(start: DateTime<Utc>, runs: &[usize]) -> LoadProfile {
+        LoadProfile {
+            buckets: runs
+                .iter()
+                .enumerate()
+                .map(|(index, &expected_runs)| LoadBucket {
+                    start: start + BUCKET * index as i32,
+                    expected_runs,
+                    estimated_concurrency: 0.0,
+                })
+                .collect(),
+        }
+    }
+
+    fn candidate(job: u64, next_run: DateTime<Utc>, period: Duration) -> Candidate {
+        Candidate {
+            job: JobHandle(job),
+            next_run,
+            period,
+            pinned: false,
+        }
+    }
+
+    #[test]
+    fn jobs_in_the_same_bucket_are_spread() {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let suggestions = suggest(
+            &profile(start, &[2, 1]),
+            vec![
+                candidate(0, start, Duration::minutes(10)),
+                candidate(1, start + Duration::seconds(1), Duration::minutes(10)),
+                candidate(2, start + BUCKET, Duration::minutes(10)),
+            ],
+        );
+        assert_eq!(
+            suggestions,
+            [OffsetSuggestion {
+                job: JobHandle(1),
+                offset: Duration::seconds(29),
+                cluster_size: 2,
+            }]
+        );
+    }
+
+    #[test]
+    fn clusters_too_large_for_their_period_are_left_alone() {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let candidates = (0..20)
+            .map(|job| candidate(job, start, Duration::milliseconds(100)))
+            .collect();
+        assert!(suggest(&profile(start, &[20]), candidates).is_empty());
+    }
+}
@@ -0,0 +1,92 @@
+use crate::id::JobId;
+use crate::run::RunRecord;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+/// Scheduling state of one job, as persisted by a [`JobStore`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JobState {
+    pub job_id: JobId,
+    pub next_run: Option<DateTime<Utc>>,
+    pub remaining_runs: Option<i32>,
+}
+
+/// When a job's consumed occurrence is written to the [`JobStore`], relative
+/// to running its task. Only matters if the process dies mid-run.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Delivery {
+    /// Persist the advanced job state, then run. An occurrence interrupted by
+    /// a crash is not repeated after a restart.
+    #[default]
+    AtMostOnce,
+    /// Run, then persist the advanced job state together with the run record.
+    /// An occurrence interrupted by a crash runs again after a restart.
+    AtLeastOnce,
+}
+
+#[derive(Clone, Debug)]
+pub enum StoreUpdate {
+    Job(JobState),
+    Run(RunRecord),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StoreError(String);
+
+impl StoreError {
+    pub fn new(message: impl Into<String>) -> Self {
+        StoreError(message.into())
+    }
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Error for StoreError {}
+
+/// Persistence for job state and finished runs.
+///
+/// Jobs are matched by [`JobId`], so restoring state across restarts needs an
+/// [`IdGenerator`](crate::IdGenerator) that hands out the same ids each time.
+pub trait JobStore: Send {
+    fn save(&mut self, update: &StoreUpdate) -> Result<(), StoreError>;
+    fn load(&mut self, job_id: &JobId) -> Result<Option<JobState>, StoreError>;
+}
+
+/// Keeps everything in memory; useful for tests.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    jobs: HashMap<JobId, JobState>,
+    runs: Vec<RunRecord>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn runs(&self) -> &[RunRecord] {
+        &self.runs
+    }
+}
+
+impl JobStore for MemoryStore {
+    fn save(&mut self, update: &StoreUpdate) -> Result<(), StoreError> {
+        match update {
+            StoreUpdate::Job(state) => {
+                self.jobs.insert(state.job_id.clone(), state.clone());
+            }
+            StoreUpdate::Run(record) => self.runs.push(record.clone()),
+        }
+        Ok(())
+    }
+
+    fn load(&mut self, job_id: &JobId) -> Result<Option<JobState>, StoreError> {
+        Ok(self.jobs.get(job_id).cloned())
+    }
+}
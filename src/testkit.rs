@@ -0,0 +1,267 @@
+//! Helpers for testing [`Scheduler`] implementations against the laws the
+//! built-in schedules satisfy:
+//!
+//! - `first_run(now)` is never before `now`;
+//! - `next_after(t)` is strictly after `t`;
+//! - `next_after` is monotone: `a <= b` implies `next_after(a) <= next_after(b)`;
+//! - every produced fire time satisfies the schedule's own constraints.
+//!
+//! Inputs are pseudo-random but seeded, so failures are reproducible.
+//!
+//! It also provides fault injectors for job tasks: [`fail_every_nth`],
+//! [`panic_once`] and [`delay`].
+
+mod faults;
+
+pub use faults::{delay, fail_every_nth, panic_once};
+
+use crate::job::Scheduler;
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use std::error::Error;
+use std::fmt;
+
+/// A fire time that broke one of the schedule laws.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LawViolation {
+    pub law: &'static str,
+    pub input: DateTime<Utc>,
+    pub output: Option<DateTime<Utc>>,
+}
+
+impl fmt::Display for LawViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.output {
+            Some(output) => write!(f, "{}: {} produced {}", self.law, self.input, output),
+            None => write!(f, "{}: {} produced nothing", self.law, self.input),
+        }
+    }
+}
+
+impl Error for LawViolation {}
+
+/// `count` instants spread pseudo-randomly over `[from, to)`, sorted, derived from `seed`.
+pub fn sample_instants(
+    seed: u64,
+    count: usize,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Vec<DateTime<Utc>> {
+    let span = (to - from).num_milliseconds().max(1) as u64;
+    let mut state = seed;
+    let mut instants: Vec<_> = (0..count)
+        .map(|_| from + Duration::milliseconds((splitmix64(&mut state) % span) as i64))
+        .collect();
+    instants.sort();
+    instants
+}
+
+/// 1000 seeded instants between 2020 and 2030.
+pub fn default_instants() -> Vec<DateTime<Utc>> {
+    sample_instants(
+        0x5eed,
+        1000,
+        Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap(),
+        Utc.with_ymd_and_hms(2030, 1, 1, 0, 0, 0).unwrap(),
+    )
+}
+
+pub fn check_first_run_not_before_now<S: Scheduler + ?Sized>(
+    schedule: &S,
+    instants: &[DateTime<Utc>],
+) -> Result<(), LawViolation> {
+    for &now in instants {
+        let first = schedule.first_run(now);
+        if first.is_some_and(|first| first < now) {
+            return Err(LawViolation {
+                law: "first_run is not before now",
+                input: now,
+                output: first,
+            });
+        }
+    }
+    Ok(())
+}
+
+pub fn check_next_after_is_later<S: Scheduler + ?Sized>(
+    schedule: &S,
+    instants: &[DateTime<Utc>],
+) -> Result<(), LawViolation> {
+    for &after in instants {
+        let next = schedule.next_after(after);
+        if next.is_some_and(|next| next <= after) {
+            return Err(LawViolation {
+                law: "next_after is strictly after its input",
+                input: after,
+                output: next,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// `instants` must be sorted.
+pub fn check_next_after_is_monotone<S: Scheduler + ?Sized>(
+    schedule: &S,
+    instants: &[DateTime<Utc>],
+) -> Result<(), LawViolation> {
+    let mut previous: Option<DateTime<Utc>> = None;
+    for &after in instants {
+        let next = schedule.next_after(after);
+        match (previous, next) {
+            (Some(previous), Some(next)) if next < previous => {
+                return Err(LawViolation {
+                    law: "next_after is monotone",
+                    input: after,
+                    output: Some(next),
+                });
+            }
+            (Some(_), None) => {}
+            _ => previous = next,
+        }
+    }
+    Ok(())
+}
+
+/// Checks that every time produced by `first_run` and `next_after` satisfies
+/// `constraint`, e.g. `|t| t.weekday() == Weekday::Mon`.
+pub fn check_constraint<S, F>(
+    schedule: &S,
+    instants: &[DateTime<Utc>],
+    constraint: F,
+) -> Result<(), LawViolation>
+where
+    S: Scheduler + ?Sized,
+    F: Fn(DateTime<Utc>) -> bool,
+{
+    for &input in instants {
+        for output in [schedule.first_run(input), schedule.next_after(input)] {
+            if output.is_some_and(|output| !constraint(output)) {
+                return Err(LawViolation {
+                    law: "fire times satisfy the schedule's constraints",
+                    input,
+                    output,
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Runs every generic law over `instants`.
+pub fn check_laws<S: Scheduler + ?Sized>(
+    schedule: &S,
+    instants: &[DateTime<Utc>],
+) -> Result<(), LawViolation> {
+    check_first_run_not_before_now(schedule, instants)?;
+    check_next_after_is_later(schedule, instants)?;
+    check_next_after_is_monotone(schedule, instants)
+}
+
+/// Panics with the first violated law, checked over [`default_instants`].
+pub fn assert_laws<S: Scheduler + ?Sized>(schedule: &S) {
+    if let Err(violation) = check_laws(schedule, &default_instants()) {
+        panic!(
+            "{} violates a schedule law: {violation}",
+            schedule.describe()
+        );
+    }
+}
+
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dynamic::TimeWindow;
+    use crate::schedule::OnceAt;
+    use crate::{Schedule, TimeUnit};
+    use chrono::{Datelike, NaiveTime, Timelike, Weekday};
+
+    fn time(hour: u32, minute: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    fn assert_constraint<S, F>(schedule: &S, constraint: F)
+    where
+        S: Scheduler + ?Sized,
+        F: Fn(DateTime<Utc>) -> bool,
+    {
+        if let Err(violation) = check_constraint(schedule, &default_instants(), constraint) {
+            panic!("{}: {violation}", schedule.describe());
+        }
+    }
+
+    #[test]
+    fn intervals_satisfy_the_laws() {
+        for unit in [TimeUnit::Seconds, TimeUnit::Minutes, TimeUnit::Hours] {
+            assert_laws(&Schedule::new(5, unit));
+        }
+        assert_laws(&Schedule::new(10, TimeUnit::Minutes).phase(Duration::minutes(3)));
+    }
+
+    #[test]
+    fn daily_at_fires_at_the_at_time() {
+        let schedule = Schedule::new(1, TimeUnit::Days).at(time(10, 0));
+        assert_laws(&schedule);
+        assert_constraint(&schedule, |at| at.time() == time(10, 0));
+
+        let now = Utc.with_ymd_and_hms(2024, 3, 5, 11, 0, 0).unwrap();
+        assert_eq!(
+            schedule.first_run(now),
+            Some(Utc.with_ymd_and_hms(2024, 3, 6, 10, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn weekly_on_a_day_fires_on_that_day() {
+        let schedule = Schedule::new(1, TimeUnit::Weeks)
+            .on(Weekday::Mon)
+            .at(time(8, 30));
+        assert_laws(&schedule);
+        assert_constraint(&schedule, |at| {
+            at.weekday() == Weekday::Mon && at.time() == time(8, 30)
+        });
+    }
+
+    #[test]
+    fn windows_and_weekdays_are_respected() {
+        let schedule = Schedule::new(10, TimeUnit::Minutes)
+            .between(time(9, 0), time(17, 0))
+            .except_weekends();
+        assert_laws(&schedule);
+        assert_constraint(&schedule, |at| {
+            (9..17).contains(&at.hour()) && !matches!(at.weekday(), Weekday::Sat | Weekday::Sun)
+        });
+    }
+
+    #[test]
+    fn time_window_fires_inside_the_window() {
+        let window = TimeWindow::new(time(9, 0), time(17, 0), Duration::minutes(30));
+        assert_laws(&window);
+        assert_constraint(&window, |at| (9..17).contains(&at.hour()));
+    }
+
+    #[test]
+    fn once_at_fires_once() {
+        let at = Utc.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap();
+        let once = OnceAt(at);
+        assert_laws(&once);
+        assert_constraint(&once, |fired| fired >= at);
+    }
+
+    #[cfg(feature = "solar")]
+    #[test]
+    fn solar_fires_at_the_event() {
+        use crate::{Solar, SolarEvent};
+
+        let solar = Solar::new(SolarEvent::Sunrise, 51.5, -0.1).offset(Duration::minutes(-15));
+        assert_laws(&solar);
+        assert_constraint(&solar, |at| solar.first_run(at) == Some(at));
+    }
+}
@@ -0,0 +1,60 @@
+//! Task wrappers that inject failures and delays deterministically, so retry
+//! and failure-handling paths can be exercised from tests. Failures are
+//! panics, which the runner records as [`JobOutcome::Panicked`](crate::JobOutcome::Panicked).
+//! Wrappers compose: `panic_once(delay(range, 7, task))`.
+
+use super::splitmix64;
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Panics instead of running `task` on every `n`th call (the `n`th, `2n`th, ...).
+///
+/// # Panics
+///
+/// Panics if `n` is zero.
+pub fn fail_every_nth<F>(n: u64, task: F) -> impl Fn() + Send + Sync + 'static
+where
+    F: Fn() + Send + Sync + 'static,
+{
+    assert!(n > 0, "fail_every_nth expects n > 0");
+    let calls = AtomicU64::new(0);
+    move || {
+        let call = calls.fetch_add(1, Ordering::SeqCst) + 1;
+        if call.is_multiple_of(n) {
+            panic!("injected failure on call {call}");
+        }
+        task()
+    }
+}
+
+/// Panics on the first call, then runs `task` normally.
+pub fn panic_once<F>(task: F) -> impl Fn() + Send + Sync + 'static
+where
+    F: Fn() + Send + Sync + 'static,
+{
+    let panicked = AtomicBool::new(false);
+    move || {
+        if !panicked.swap(true, Ordering::SeqCst) {
+            panic!("injected panic on first call");
+        }
+        task()
+    }
+}
+
+/// Sleeps for a pseudo-random duration in `range` before each call of `task`.
+/// The sequence of delays is determined by `seed`.
+pub fn delay<F>(range: Range<Duration>, seed: u64, task: F) -> impl Fn() + Send + Sync + 'static
+where
+    F: Fn() + Send + Sync + 'static,
+{
+    let state = Mutex::new(seed);
+    let min = range.start.as_micros() as u64;
+    let span = (range.end.as_micros() as u64).saturating_sub(min).max(1);
+    move || {
+        let offset = splitmix64(&mut state.lock().unwrap()) % span;
+        std::thread::sleep(Duration::from_micros(min + offset));
+        task()
+    }
+}
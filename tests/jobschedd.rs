@@ -0,0 +1,198 @@
+//! Runs the `jobschedd` binary against a small config and checks its admin
+//! API and state file.
+
+#![cfg(unix)]
+
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+struct Daemon {
+    child: Child,
+    addr: String,
+    dir: PathBuf,
+}
+
+impl Daemon {
+    fn start(name: &str, jobs: &str) -> Daemon {
+        let dir = std::env::temp_dir().join(format!("jobschedd-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let addr = free_addr();
+        let config = format!(
+            "[daemon]\nstate_file = {:?}\nadmin_addr = \"{addr}\"\n\n{jobs}",
+            dir.join("state")
+        );
+        let config_path = dir.join("jobschedd.toml");
+        std::fs::write(&config_path, config).unwrap();
+        let child = Command::new(env!("CARGO_BIN_EXE_jobschedd"))
+            .arg(&config_path)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap();
+        let daemon = Daemon { child, addr, dir };
+        daemon.wait_until(|daemon| daemon.try_request("GET", "/jobs").is_some());
+        daemon
+    }
+
+    fn try_request(&self, method: &str, path: &str) -> Option<(u16, String)> {
+        let mut stream = TcpStream::connect(&self.addr).ok()?;
+        write!(stream, "{method} {path} HTTP/1.1\r\nHost: test\r\n\r\n").ok()?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response).ok()?;
+        let status = response.split(' ').nth(1)?.parse().ok()?;
+        let (_, body) = response.split_once("\r\n\r\n")?;
+        Some((status, body.to_string()))
+    }
+
+    fn request(&self, method: &str, path: &str) -> (u16, String) {
+        self.try_request(method, path)
+            .unwrap_or_else(|| panic!("{method} {path} got no response"))
+    }
+
+    fn wait_until(&self, done: impl Fn(&Daemon) -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !done(self) {
+            assert!(Instant::now() < deadline, "timed out waiting for jobschedd");
+            std::thread::sleep(Duration::from_millis(50));
+        }
+    }
+
+    fn state_file(&self) -> PathBuf {
+        self.dir.join("state")
+    }
+}
+
+impl Drop for Daemon {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn free_addr() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.local_addr().unwrap().to_string()
+}
+
+fn metric(metrics: &str, name: &str, job: &str) -> Option<u64> {
+    let prefix = format!("{name}{{job=\"{job}\"}} ");
+    metrics
+        .lines()
+        .find_map(|line| line.strip_prefix(&prefix))
+        .and_then(|value| value.parse().ok())
+}
+
+fn state_lines(path: &Path) -> Vec<Vec<String>> {
+    std::fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .map(|line| line.split('\t').map(str::to_string).collect())
+        .collect()
+}
+
+const JOBS: &str = r#"
+[[job]]
+name = "pass"
+every = "1 hour"
+command = "true"
+
+[[job]]
+name = "fail"
+every = "1 hour"
+command = "false"
+"#;
+
+#[test]
+fn admin_api_and_state_file() {
+    let mut daemon = Daemon::start("admin", JOBS);
+
+    let (status, jobs) = daemon.request("GET", "/jobs");
+    assert_eq!(status, 200);
+    let names: Vec<_> = jobs
+        .lines()
+        .map(|line| line.split('\t').next().unwrap())
+        .collect();
+    assert_eq!(names, ["pass", "fail"]);
+    assert!(jobs.lines().all(|line| line.ends_with("\tevery hour")));
+
+    // Both jobs run once straight away, then hourly.
+    daemon.wait_until(|daemon| {
+        let (_, metrics) = daemon.request("GET", "/metrics");
+        metric(&metrics, "jobschedd_runs_total", "pass") == Some(1)
+            && metric(&metrics, "jobschedd_runs_total", "fail") == Some(1)
+    });
+    let (status, metrics) = daemon.request("GET", "/metrics");
+    assert_eq!(status, 200);
+    assert_eq!(
+        metric(&metrics, "jobschedd_failures_total", "pass"),
+        Some(0)
+    );
+    assert_eq!(
+        metric(&metrics, "jobschedd_failures_total", "fail"),
+        Some(1)
+    );
+
+    assert_eq!(
+        daemon.request("POST", "/jobs/fail/trigger"),
+        (200, "Triggered\n".to_string())
+    );
+    daemon.wait_until(|daemon| {
+        let (_, metrics) = daemon.request("GET", "/metrics");
+        metric(&metrics, "jobschedd_failures_total", "fail") == Some(2)
+    });
+    assert_eq!(
+        daemon.request("POST", "/jobs/missing/trigger"),
+        (404, "no job named `missing`\n".to_string())
+    );
+    assert_eq!(daemon.request("GET", "/jobs/fail/trigger").0, 405);
+    assert_eq!(daemon.request("DELETE", "/jobs").0, 405);
+    assert_eq!(daemon.request("GET", "/nothing").0, 404);
+
+    // The state file holds each job's next run, an hour after its first.
+    let next_hour = chrono::Utc::now() + chrono::Duration::minutes(50);
+    let rescheduled = |line: &Vec<String>| {
+        line.get(1)
+            .and_then(|next_run| chrono::DateTime::parse_from_rfc3339(next_run).ok())
+            .is_some_and(|next_run| next_run > next_hour)
+    };
+    daemon.wait_until(|daemon| {
+        let state = state_lines(&daemon.state_file());
+        state.len() == 2 && state.iter().all(rescheduled)
+    });
+    let state = state_lines(&daemon.state_file());
+    for (line, name) in state.iter().zip(["fail", "pass"]) {
+        assert_eq!(line.len(), 3, "{line:?}");
+        assert_eq!(line[0], name);
+        assert_eq!(line[2], "-");
+    }
+
+    assert_eq!(
+        daemon.request("POST", "/shutdown"),
+        (200, "shutting down\n".to_string())
+    );
+    let status = daemon.child.wait().unwrap();
+    assert!(status.success(), "{status}");
+}
+
+#[test]
+fn names_with_special_characters() {
+    let daemon = Daemon::start(
+        "labels",
+        "[[job]]\nname = 'say \"hi\" \\ bye'\nevery = \"1 hour\"\ncommand = \"true\"\n",
+    );
+    daemon.wait_until(|daemon| {
+        let (_, metrics) = daemon.request("GET", "/metrics");
+        metric(&metrics, "jobschedd_runs_total", r#"say \"hi\" \\ bye"#) == Some(1)
+    });
+    assert_eq!(
+        daemon.request("POST", "/jobs/say%20%22hi%22%20%5C%20bye/trigger"),
+        (200, "Triggered\n".to_string())
+    );
+    assert_eq!(daemon.request("POST", "/jobs/say%2/trigger").0, 400);
+    assert_eq!(daemon.request("POST", "/heartbeats/%E2%9C%93").0, 200);
+}